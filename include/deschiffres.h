@@ -0,0 +1,41 @@
+/* C interface of deschiffres, built with the `ffi` feature:
+ *   cargo build --release --features ffi
+ * then link with target/release/libdeschiffres.so (or .dylib, .dll)
+ *
+ * Memory: a CSolution returned by deschiffres_solve belongs to the caller,
+ * who must give it back to deschiffres_free exactly once (never to free()).
+ * The expression string belongs to the solution, and is freed with it.
+ */
+
+#ifndef DESCHIFFRES_H
+#define DESCHIFFRES_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct CSolution {
+  int32_t value;
+  /* The calculus as a null terminated infix expression, eg: (2 * (50 + 25)) */
+  char *expression;
+} CSolution;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Solve with `len` numbers read from `numbers`, accepting a result up to
+ * `approximation` away from `target`
+ * Returns NULL when there is no match (or the input is invalid) */
+CSolution *deschiffres_solve(const int32_t *numbers,
+                             size_t len,
+                             int32_t target,
+                             int32_t approximation);
+
+/* Free a solution returned by deschiffres_solve (nothing is done for NULL) */
+void deschiffres_free(CSolution *solution);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* DESCHIFFRES_H */
@@ -1,24 +1,110 @@
+use std::process::exit;
 use std::thread::available_parallelism;
 use std::time::Instant;
 
 use deschiffres::all_combinations;
 
+use cli::{parse_value, Arguments};
+
+mod cli;
+
 const LOOPS: usize = 30;
+const SPEC: [i32; 6] = [5, 25, 2, 50, 100, 10];
+
+const USAGE: &str = "Usage: benchmark [OPTIONS] [NUMBERS]...
+
+Time all_combinations with 0 to MAX_WORKERS - 1 workers
+
+Arguments:
+  [NUMBERS]...  The numbers to combine [default: 5 25 2 50 100 10]
+
+Options:
+  -l, --loops <LOOPS>              Searches timed for each number of workers [default: 30]
+  -w, --max-workers <MAX_WORKERS>  Numbers of workers to try [default: the number of cores]
+  -h, --help                       Print help";
+
+struct Args {
+    spec: Vec<i32>,
+    loops: usize,
+    max_workers: Option<usize>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Arguments::from_env();
+
+    let mut spec = vec![];
+    let mut loops = LOOPS;
+    let mut max_workers = None;
+
+    while let Some((option, value)) = args.next() {
+        match option.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            "-l" | "--loops" => loops = args.value(&option, value)?,
+            "-w" | "--max-workers" => max_workers = Some(args.value(&option, value)?),
+            _ if option.starts_with('-') => {
+                return Err(format!("unexpected argument '{option}'"));
+            }
+            _ => spec.push(parse_value("[NUMBERS]", Some(option))?),
+        }
+    }
+
+    if spec.is_empty() {
+        spec = SPEC.to_vec();
+    }
+
+    Ok(Args {
+        spec,
+        loops,
+        max_workers,
+    })
+}
 
 fn main() {
-    let spec = [5, 25, 2, 50, 100, 10];
+    let Args {
+        spec,
+        loops,
+        max_workers,
+    } = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("error: {err}\n\nFor more information, try '--help'");
+            exit(1);
+        }
+    };
 
     let ncores = match available_parallelism() {
         Ok(x) => std::cmp::max(1, x.get()),
         Err(_) => 1,
     };
+    let max_workers = max_workers.unwrap_or(ncores);
 
-    for w in 0..ncores {
+    println!("Benchmarking {spec:?}, {loops} searches each");
+
+    for w in 0..max_workers {
         let start = Instant::now();
-        for _ in 0..LOOPS {
-            all_combinations(&spec, w);
+        for _ in 0..loops {
+            if let Err(err) = all_combinations(&spec, w) {
+                eprintln!("Could not solve: {err}");
+                exit(1);
+            }
         }
         let end = Instant::now();
         println!("max={w} workers, solved in {:?}", end - start);
     }
+
+    #[cfg(feature = "rayon")]
+    {
+        let start = Instant::now();
+        for _ in 0..loops {
+            if let Err(err) = deschiffres::all_combinations_rayon(&spec) {
+                eprintln!("Could not solve: {err}");
+                exit(1);
+            }
+        }
+        let end = Instant::now();
+        println!("rayon, solved in {:?}", end - start);
+    }
 }
@@ -0,0 +1,42 @@
+// Command line parsing, shared by the deschiffres and benchmark binaries
+use std::str::FromStr;
+
+// The command line arguments, one at a time (without the program name)
+pub struct Arguments {
+    args: std::iter::Skip<std::env::Args>,
+}
+
+impl Arguments {
+    pub fn from_env() -> Self {
+        Arguments {
+            args: std::env::args().skip(1),
+        }
+    }
+
+    // The value of `option`: the one given with --option=value, or else the next argument
+    pub fn value<T: FromStr>(&mut self, option: &str, value: Option<String>) -> Result<T, String> {
+        parse_value(option, value.or_else(|| self.args.next()))
+    }
+}
+
+// An option (or an argument) with its value: --option=value is the same as --option value
+impl Iterator for Arguments {
+    type Item = (String, Option<String>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let argument = self.args.next()?;
+        Some(match argument.split_once('=') {
+            Some((option, value)) if option.starts_with("--") => {
+                (option.to_string(), Some(value.to_string()))
+            }
+            _ => (argument, None),
+        })
+    }
+}
+
+pub fn parse_value<T: FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("a value is required for '{option}'"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{value}' for '{option}'"))
+}
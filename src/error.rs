@@ -0,0 +1,203 @@
+use crossbeam_channel::SendError;
+
+use crate::{Integer, MOperation};
+
+// Everything that can go wrong while solving
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolveError {
+    // There is no number to combine
+    EmptyInput,
+    // More numbers than allowed (see `SolveOptions::max_tiles`)
+    TooManyTiles { count: usize, max: usize },
+    // A worker channel was closed while the search was still running
+    ChannelClosed,
+    // Something that should never happen (with some context)
+    Internal(String),
+}
+
+impl std::fmt::Display for SolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SolveError::EmptyInput => write!(f, "no number to combine"),
+            SolveError::TooManyTiles { count, max } => write!(
+                f,
+                "{count} numbers to combine, at most {max} are allowed: \
+                 the search grows exponentially with each number"
+            ),
+            SolveError::ChannelClosed => write!(f, "a worker channel was closed unexpectedly"),
+            SolveError::Internal(context) => write!(f, "internal error: {context}"),
+        }
+    }
+}
+
+impl std::error::Error for SolveError {}
+
+impl<T> From<SendError<T>> for SolveError {
+    fn from(_: SendError<T>) -> Self {
+        SolveError::ChannelClosed
+    }
+}
+
+// Why an operation is not allowed by the rules of the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+    // The result does not fit in the integer type
+    Overflow,
+    // The result (or the divisor) is not strictly positive
+    NotPositive,
+    DivisionByZero,
+    // The division is not exact, eg: 7 / 2
+    NotInteger,
+    // The exponent of a power is negative, or too large
+    Exponent,
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Violation::Overflow => write!(f, "the result is too large"),
+            Violation::NotPositive => write!(f, "only positive numbers are allowed"),
+            Violation::DivisionByZero => write!(f, "division by zero"),
+            Violation::NotInteger => write!(f, "the result is not an integer"),
+            Violation::Exponent => {
+                write!(f, "the exponent must be from 0 to {}", crate::MAX_EXPONENT)
+            }
+        }
+    }
+}
+
+// Everything that can go wrong when evaluating an expression
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    // A character that was not expected there, with its position (in characters)
+    UnexpectedChar(char, usize),
+    // The expression is incomplete, eg: "(2 + 3"
+    UnexpectedEnd,
+    // A number that does not fit in the integer type
+    InvalidNumber(String),
+    // Not one of + - * / ^ % |
+    UnknownOperation(String),
+    // Too many nested parentheses (or powers), or too many operations,
+    // with the position where the limit was reached
+    TooDeep(usize),
+    // A valid expression, with an operation not allowed by the rules
+    Forbidden(MOperation, Violation),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedChar(c, position) => {
+                write!(f, "unexpected '{c}' at position {position}")
+            }
+            ParseError::UnexpectedEnd => write!(f, "unexpected end of expression"),
+            ParseError::InvalidNumber(number) => write!(f, "{number} is not a valid number"),
+            ParseError::UnknownOperation(op) => {
+                write!(
+                    f,
+                    "'{op}' is not an operation (expected one of + - * / ^ % |)"
+                )
+            }
+            ParseError::TooDeep(position) => {
+                write!(f, "too deeply nested at position {position}")
+            }
+            ParseError::Forbidden(MOperation(op, a, b), violation) => {
+                write!(f, "{a} {op} {b} is not allowed: {violation}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Why a Number does not hold
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerifyError<N = i32> {
+    // An operation is not allowed by the rules
+    Forbidden(MOperation<N>, Violation),
+    // A value that is not what the operations lead to
+    // (an operand of the next operation, or the value of the Number itself)
+    Mismatch { expected: N, found: N },
+    // An operation whose result is not an operand of the ones before it
+    // (see `Number::from_operations`)
+    Unused(MOperation<N>),
+}
+
+impl<N: Integer> std::fmt::Display for VerifyError<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VerifyError::Forbidden(MOperation(op, a, b), violation) => {
+                write!(f, "{a} {op} {b} is not allowed: {violation}")
+            }
+            VerifyError::Mismatch { expected, found } => {
+                write!(f, "found {found} where the operations lead to {expected}")
+            }
+            VerifyError::Unused(MOperation(op, a, b)) => {
+                write!(f, "the result of {a} {op} {b} is not used")
+            }
+        }
+    }
+}
+
+impl<N: Integer> std::error::Error for VerifyError<N> {}
+
+// Everything that can go wrong when generating a puzzle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PuzzleError {
+    // There are only 4 large tiles
+    TooManyLarge(usize),
+    // A puzzle has 6 tiles
+    TileCount(usize),
+    // No target can be reached with those numbers
+    NoTarget(Vec<i32>),
+    Solve(SolveError),
+}
+
+impl std::fmt::Display for PuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PuzzleError::TooManyLarge(count) => {
+                write!(f, "{count} large tiles asked, there are only 4")
+            }
+            PuzzleError::TileCount(count) => write!(f, "{count} tiles asked instead of 6"),
+            PuzzleError::NoTarget(numbers) => {
+                write!(f, "no target can be reached with {numbers:?}")
+            }
+            PuzzleError::Solve(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
+impl From<SolveError> for PuzzleError {
+    fn from(err: SolveError) -> Self {
+        PuzzleError::Solve(err)
+    }
+}
+
+// Why a selection of tiles is not allowed (see `validate_tiles`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TileError {
+    // Not the number of tiles of a game
+    Count { expected: usize, found: usize },
+    // There is no such tile
+    Unknown(i32),
+    // This tile is there more times than the game has it
+    TooMany { tile: i32, max: usize },
+}
+
+impl std::fmt::Display for TileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TileError::Count { expected, found } => {
+                write!(f, "{found} tiles instead of {expected}")
+            }
+            TileError::Unknown(tile) => write!(f, "{tile} is not a tile"),
+            TileError::TooMany { tile, max: 1 } => write!(f, "{tile} can only be used once"),
+            TileError::TooMany { tile, max } => write!(f, "{tile} can only be used {max} times"),
+        }
+    }
+}
+
+impl std::error::Error for TileError {}
@@ -0,0 +1,506 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::number::OpNode;
+use crate::{Integer, MOperation, Number, Operation, ParseError, Rules};
+
+// A Number operations, as a tree (see `Number::to_expr`)
+// The search does not use it: Numbers share their operations instead (see `OpNode`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr<N = i32> {
+    Value(N),
+    Op {
+        op: Operation,
+        lhs: Box<Expr<N>>,
+        rhs: Box<Expr<N>>,
+    },
+}
+
+impl<N: Integer> Expr<N> {
+    pub(crate) fn from_number(number: &Number<N>) -> Self {
+        match number.tree() {
+            Some(node) => Self::from_node(node),
+            None => Expr::Value(number.value),
+        }
+    }
+
+    fn from_node(node: &OpNode<N>) -> Self {
+        let operand = |child: Option<&OpNode<N>>, value| match child {
+            Some(child) => Self::from_node(child),
+            None => Expr::Value(value),
+        };
+
+        Expr::Op {
+            op: node.operation.0,
+            lhs: Box::new(operand(node.lhs.as_deref(), node.operation.1)),
+            rhs: Box::new(operand(node.rhs.as_deref(), node.operation.2)),
+        }
+    }
+
+    // Infix notation with only the parentheses needed, eg: 2 * (50 + 25) or 50 + 25 + 2
+    // Powers come first, then multiplications, divisions and modulos, then operations are made
+    // from left to right (powers from right to left): an operand gets parentheses when
+    // its operation comes after the one it is an operand of, when it is on the left of a power,
+    // or when it is on the right of an operation of the same precedence, unless it is the
+    // same addition, multiplication or power (a + (b + c) is a + b + c, a ^ (b ^ c) is a ^ b ^ c),
+    // eg: 50 - (25 - 2) and 3 * (7 % 4), but (50 - 25) - 2 is 50 - 25 - 2
+    // Concatenations are written as the number they make, eg: 25 * 4 for (2 | 5) * 4
+    pub fn as_infix(&self) -> String {
+        match self {
+            Expr::Value(value) => value.to_string(),
+            Expr::Op {
+                op: Operation::Concatenation,
+                lhs,
+                rhs,
+            } => format!("{}{}", lhs.as_infix(), rhs.as_infix()),
+            Expr::Op { op, lhs, rhs } => {
+                let lhs = lhs.infix_operand(*op, false);
+                let rhs = rhs.infix_operand(*op, true);
+                format!("{lhs} {op} {rhs}")
+            }
+        }
+    }
+
+    // This expression as the left (or `right`) operand of a `parent` operation
+    fn infix_operand(&self, parent: Operation, right: bool) -> String {
+        let enclose = match self {
+            Expr::Value(_) => false,
+            Expr::Op { op, .. } if precedence(*op) == precedence(parent) => {
+                if right {
+                    *op != parent
+                        || !matches!(
+                            parent,
+                            Operation::Addition | Operation::Multiplication | Operation::Power
+                        )
+                } else {
+                    parent == Operation::Power
+                }
+            }
+            Expr::Op { op, .. } => precedence(*op) < precedence(parent),
+        };
+
+        if enclose {
+            format!("({})", self.as_infix())
+        } else {
+            self.as_infix()
+        }
+    }
+
+    // The value of the expression under the default rules of the game (see `Operation::apply`),
+    // None if any of its operations is not allowed
+    pub fn eval(&self) -> Option<N> {
+        match self {
+            Expr::Value(value) => Some(*value),
+            Expr::Op { op, lhs, rhs } => op.apply(lhs.eval()?, rhs.eval()?),
+        }
+    }
+
+    // Infix notation, with every operation between parentheses
+    // (but concatenations, that are only written as the number they make, eg: 25)
+    pub fn as_expression(&self) -> String {
+        match self {
+            Expr::Value(value) => value.to_string(),
+            Expr::Op {
+                op: Operation::Concatenation,
+                lhs,
+                rhs,
+            } => format!("{}{}", lhs.as_expression(), rhs.as_expression()),
+            Expr::Op { op, lhs, rhs } => {
+                format!("({} {} {})", lhs.as_expression(), op, rhs.as_expression())
+            }
+        }
+    }
+
+    // The same calculus in a single form, whatever the order of its commutative operands
+    // and how its associative chains are nested: a chain of additions (or multiplications)
+    // becomes a single one, nested on the left, with its operands sorted, input numbers
+    // first (smallest first) then the other operands (by `canonical_key`),
+    // eg: (2 + (50 + 25)) and ((25 + 2) + 50) both become ((2 + 25) + 50)
+    // Operands of subtractions and divisions stay in place (only simplified themselves)
+    pub fn simplify(&self) -> Self {
+        match self {
+            Expr::Value(value) => Expr::Value(*value),
+            Expr::Op {
+                op: op @ (Operation::Addition | Operation::Multiplication),
+                ..
+            } => {
+                let mut operands = vec![];
+                self.chain(*op, &mut operands);
+                operands.sort_by_cached_key(|x| match x {
+                    Expr::Value(value) => (false, Some(*value), String::new()),
+                    _ => (true, None, x.canonical_key()),
+                });
+
+                operands
+                    .into_iter()
+                    .reduce(|lhs, rhs| Expr::Op {
+                        op: *op,
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    })
+                    .expect("an operation has operands")
+            }
+            Expr::Op { op, lhs, rhs } => Expr::Op {
+                op: *op,
+                lhs: Box::new(lhs.simplify()),
+                rhs: Box::new(rhs.simplify()),
+            },
+        }
+    }
+
+    // The simplified operands of a chain of `op`
+    fn chain(&self, op: Operation, operands: &mut Vec<Self>) {
+        match self {
+            Expr::Op {
+                op: inner,
+                lhs,
+                rhs,
+            } if *inner == op => {
+                lhs.chain(op, operands);
+                rhs.chain(op, operands);
+            }
+            _ => operands.push(self.simplify()),
+        }
+    }
+
+    // A form shared by every expression equal up to commutativity and associativity
+    // Chains of additions (and of multiplications) are flattened into a single list
+    // of operands, and those operands are sorted by their own key (in string order),
+    // eg: ((25 + 50) + 2) and (2 + (50 + 25)) both become (2 + 25 + 50)
+    // Subtractions and divisions keep their operands in place
+    pub(crate) fn canonical_key(&self) -> String {
+        match self {
+            Expr::Value(value) => value.to_string(),
+            Expr::Op { op, lhs, rhs } => match op {
+                Operation::Addition | Operation::Multiplication => {
+                    let mut operands = vec![];
+                    lhs.flatten(*op, &mut operands);
+                    rhs.flatten(*op, &mut operands);
+                    operands.sort();
+
+                    format!("({})", operands.join(&format!(" {op} ")))
+                }
+                _ => format!("({} {} {})", lhs.canonical_key(), op, rhs.canonical_key()),
+            },
+        }
+    }
+
+    // The keys of the operands of a chain of `op`
+    fn flatten(&self, op: Operation, operands: &mut Vec<String>) {
+        match self {
+            Expr::Op {
+                op: inner,
+                lhs,
+                rhs,
+            } if *inner == op => {
+                lhs.flatten(op, operands);
+                rhs.flatten(op, operands);
+            }
+            _ => operands.push(self.canonical_key()),
+        }
+    }
+
+    // A GraphViz digraph of the tree, with a box per input number
+    // and a node per operation (with its result), eg:
+    // n0 [label="* = 150"]; n0 -> n1; n0 -> n2; …
+    // Nodes are numbered in preorder (operation, left operand, right operand)
+    pub(crate) fn as_dot(&self) -> String {
+        let mut lines = vec!["digraph {".to_string()];
+        self.dot_node(&mut lines, &mut 0);
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+
+    // Add the lines of this node (and its operands), returns its id and value
+    fn dot_node(&self, lines: &mut Vec<String>, next: &mut usize) -> (usize, N) {
+        let id = *next;
+        *next += 1;
+
+        match self {
+            Expr::Value(value) => {
+                lines.push(format!("    n{id} [label=\"{value}\", shape=box];"));
+                (id, *value)
+            }
+            Expr::Op { op, lhs, rhs } => {
+                let position = lines.len();
+                let (lhs_id, a) = lhs.dot_node(lines, next);
+                let (rhs_id, b) = rhs.dot_node(lines, next);
+
+                let value = MOperation(*op, a, b).value();
+                let node = [
+                    format!("    n{id} [label=\"{op} = {value}\"];"),
+                    format!("    n{id} -> n{lhs_id};"),
+                    format!("    n{id} -> n{rhs_id};"),
+                ];
+                lines.splice(position..position, node);
+                (id, value)
+            }
+        }
+    }
+
+    // A LaTeX math expression, eg: 2 \times (50 + 25) or \frac{100}{4} - 2
+    // Divisions are fractions, exponents are superscripts (with any operation as a base
+    // between parentheses), other operations only get parentheses when needed:
+    // a sum (or difference) in a product, or a sum (or difference) subtracted
+    pub(crate) fn as_latex(&self) -> String {
+        match self {
+            Expr::Value(value) => value.to_string(),
+            Expr::Op {
+                op: Operation::Division,
+                lhs,
+                rhs,
+            } => format!("\\frac{{{}}}{{{}}}", lhs.as_latex(), rhs.as_latex()),
+            Expr::Op {
+                op: Operation::Concatenation,
+                lhs,
+                rhs,
+            } => format!("{}{}", lhs.as_latex(), rhs.as_latex()),
+            Expr::Op {
+                op: Operation::Power,
+                lhs,
+                rhs,
+            } => match **lhs {
+                Expr::Value(_) => format!("{}^{{{}}}", lhs.as_latex(), rhs.as_latex()),
+                _ => format!("({})^{{{}}}", lhs.as_latex(), rhs.as_latex()),
+            },
+            Expr::Op { op, lhs, rhs } => {
+                let operand = |child: &Expr<N>, is_rhs: bool| {
+                    let sum = matches!(
+                        child,
+                        Expr::Op {
+                            op: Operation::Addition | Operation::Subtraction,
+                            ..
+                        }
+                    );
+                    let modulo = matches!(
+                        child,
+                        Expr::Op {
+                            op: Operation::Modulo,
+                            ..
+                        }
+                    );
+                    let enclose = match op {
+                        Operation::Multiplication => sum || modulo,
+                        Operation::Subtraction => sum && is_rhs,
+                        // Only values, fractions and powers read as a single operand
+                        Operation::Modulo => !matches!(
+                            child,
+                            Expr::Value(_)
+                                | Expr::Op {
+                                    op: Operation::Division | Operation::Power,
+                                    ..
+                                }
+                        ),
+                        _ => false,
+                    };
+
+                    if enclose {
+                        format!("({})", child.as_latex())
+                    } else {
+                        child.as_latex()
+                    }
+                };
+
+                let symbol = match op {
+                    Operation::Multiplication => "\\times",
+                    Operation::Modulo => "\\bmod",
+                    Operation::Subtraction => "-",
+                    _ => "+",
+                };
+                format!("{} {} {}", operand(lhs, false), symbol, operand(rhs, true))
+            }
+        }
+    }
+
+    // Postfix notation, eg: 50 25 + 2 *
+    pub(crate) fn as_rpn(&self) -> String {
+        match self {
+            Expr::Value(value) => value.to_string(),
+            Expr::Op { op, lhs, rhs } => format!("{} {} {}", lhs.as_rpn(), rhs.as_rpn(), op),
+        }
+    }
+}
+
+impl Expr<i32> {
+    // Parse an infix expression of integers, eg: 2 * (50 + 25)
+    // Powers come first, then multiplications, divisions and modulos, then additions and subtractions,
+    // operations of the same precedence are made from left to right (powers from right to left)
+    pub(crate) fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            position: 0,
+            depth: 0,
+            operations: 0,
+        };
+
+        let expr = parser.sum()?;
+        match parser.next() {
+            Some(c) => Err(ParseError::UnexpectedChar(c, parser.position - 1)),
+            None => Ok(expr),
+        }
+    }
+
+    // The value of the expression, if every operation is allowed by the rules
+    pub(crate) fn evaluate(&self, rules: &Rules) -> Result<i32, ParseError> {
+        match self {
+            Expr::Value(value) => Ok(*value),
+            Expr::Op { op, lhs, rhs } => {
+                let (a, b) = (lhs.evaluate(rules)?, rhs.evaluate(rules)?);
+                rules
+                    .apply(*op, a, b)
+                    .map_err(|x| ParseError::Forbidden(MOperation(*op, a, b), x))
+            }
+        }
+    }
+}
+
+// Operations with a higher precedence are made first (the same as `Parser`)
+fn precedence(op: Operation) -> u8 {
+    match op {
+        Operation::Addition | Operation::Subtraction => 1,
+        Operation::Multiplication | Operation::Division | Operation::Modulo => 2,
+        Operation::Power => 3,
+        Operation::Concatenation => 4,
+    }
+}
+
+// Most parentheses (and powers) nested in a parsed expression: the parser is recursive,
+// and untrusted input should not be able to overflow the stack
+const MAX_NESTING: usize = 64;
+// Most operations in a parsed expression: evaluating (and dropping) the tree is recursive too,
+// and a flat chain (1 + 1 + … + 1) is as deep as it is long
+const MAX_OPERATIONS: usize = 1000;
+
+// Recursive descent parser, one method per level of precedence
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: usize,
+    // Parentheses (and powers) open at this point
+    depth: usize,
+    // Operations read so far
+    operations: usize,
+}
+
+impl Parser<'_> {
+    // The next character that is not a space
+    fn peek(&mut self) -> Option<char> {
+        while let Some(c) = self.chars.peek() {
+            if !c.is_whitespace() {
+                return Some(*c);
+            }
+            self.chars.next();
+            self.position += 1;
+        }
+        None
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.chars.next();
+        self.position += 1;
+        Some(c)
+    }
+
+    // `parse` one level of nesting deeper, or an error past `MAX_NESTING`
+    fn nested(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<Expr<i32>, ParseError>,
+    ) -> Result<Expr<i32>, ParseError> {
+        if self.depth >= MAX_NESTING {
+            return Err(ParseError::TooDeep(self.position));
+        }
+
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    // Read the operation `op`, or an error past `MAX_OPERATIONS`
+    fn operation(&mut self, op: char) -> Result<Operation, ParseError> {
+        if self.operations >= MAX_OPERATIONS {
+            return Err(ParseError::TooDeep(self.position));
+        }
+
+        self.next();
+        self.operations += 1;
+        Operation::try_from(op)
+    }
+
+    // Terms separated by + or -
+    fn sum(&mut self) -> Result<Expr<i32>, ParseError> {
+        let mut expr = self.product()?;
+
+        while let Some(op @ ('+' | '-')) = self.peek() {
+            let op = self.operation(op)?;
+            expr = Expr::Op {
+                op,
+                lhs: Box::new(expr),
+                rhs: Box::new(self.product()?),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    // Powers separated by *, / or %
+    fn product(&mut self) -> Result<Expr<i32>, ParseError> {
+        let mut expr = self.power()?;
+
+        while let Some(op @ ('*' | '/' | '%')) = self.peek() {
+            let op = self.operation(op)?;
+            expr = Expr::Op {
+                op,
+                lhs: Box::new(expr),
+                rhs: Box::new(self.power()?),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    // A factor, raised to a power if followed by ^ (2 ^ 3 ^ 2 is 2 ^ (3 ^ 2))
+    fn power(&mut self) -> Result<Expr<i32>, ParseError> {
+        let expr = self.factor()?;
+
+        if self.peek() != Some('^') {
+            return Ok(expr);
+        }
+        let op = self.operation('^')?;
+        Ok(Expr::Op {
+            op,
+            lhs: Box::new(expr),
+            rhs: Box::new(self.nested(Self::power)?),
+        })
+    }
+
+    // A number, or a whole expression between parentheses
+    fn factor(&mut self) -> Result<Expr<i32>, ParseError> {
+        match self.peek() {
+            Some('(') => {
+                let expr = self.nested(|parser| {
+                    parser.next();
+                    parser.sum()
+                })?;
+                match self.next() {
+                    Some(')') => Ok(expr),
+                    Some(c) => Err(ParseError::UnexpectedChar(c, self.position - 1)),
+                    None => Err(ParseError::UnexpectedEnd),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let mut digits = String::new();
+                while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+                    digits.push(c);
+                    self.position += 1;
+                }
+                match digits.parse() {
+                    Ok(value) => Ok(Expr::Value(value)),
+                    Err(_) => Err(ParseError::InvalidNumber(digits)),
+                }
+            }
+            Some(c) => Err(ParseError::UnexpectedChar(c, self.position)),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::ffi::{c_char, CString};
+use std::panic::catch_unwind;
+use std::ptr::null_mut;
+
+use crate::solve;
+
+// The result of `deschiffres_solve` (see include/deschiffres.h)
+#[repr(C)]
+pub struct CSolution {
+    pub value: i32,
+    // The calculus as a null terminated infix expression, eg: (2 * (50 + 25))
+    pub expression: *mut c_char,
+}
+
+/// Solve with `len` numbers read from `numbers`
+///
+/// Returns null when there is no match (or the input is invalid), otherwise a solution
+/// owned by the caller, that must be given back to `deschiffres_free` (and only once)
+///
+/// # Safety
+///
+/// `numbers` must point to `len` readable integers (or be null)
+#[no_mangle]
+pub unsafe extern "C" fn deschiffres_solve(
+    numbers: *const i32,
+    len: usize,
+    target: i32,
+    approximation: i32,
+) -> *mut CSolution {
+    if numbers.is_null() {
+        return null_mut();
+    }
+    let numbers = std::slice::from_raw_parts(numbers, len);
+
+    // Never unwind into the caller
+    let solved = catch_unwind(|| solve(numbers, target, approximation));
+
+    match solved {
+        Ok(Ok(Some(number))) => {
+            let expression = CString::new(number.as_expression())
+                .expect("an expression has no null byte")
+                .into_raw();
+
+            Box::into_raw(Box::new(CSolution {
+                value: number.value,
+                expression,
+            }))
+        }
+        _ => null_mut(),
+    }
+}
+
+/// Free a solution returned by `deschiffres_solve` (nothing is done for null)
+///
+/// # Safety
+///
+/// `solution` must come from `deschiffres_solve`, and not have been freed already
+#[no_mangle]
+pub unsafe extern "C" fn deschiffres_free(solution: *mut CSolution) {
+    if solution.is_null() {
+        return;
+    }
+
+    let solution = Box::from_raw(solution);
+    if !solution.expression.is_null() {
+        drop(CString::from_raw(solution.expression));
+    }
+}
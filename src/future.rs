@@ -0,0 +1,80 @@
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use crate::{solve_with, Number, SolveError, SolveOptions};
+
+type Solved = Result<Option<Number>, SolveError>;
+
+// What the solving thread and the future share
+#[derive(Default)]
+struct Shared {
+    solved: Option<Solved>,
+    waker: Option<Waker>,
+}
+
+// A solve running on its own thread (see `solve_async`)
+// Dropping it before it is over stops the search
+pub struct SolveFuture {
+    shared: Arc<Mutex<Shared>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Future for SolveFuture {
+    type Output = Solved;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Solved> {
+        let Ok(mut shared) = self.shared.lock() else {
+            let err = SolveError::Internal("the solving thread panicked".to_string());
+            return Poll::Ready(Err(err));
+        };
+
+        match shared.solved.take() {
+            Some(solved) => Poll::Ready(solved),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for SolveFuture {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Same as `solve`, without blocking: the search runs on a new thread,
+// and the future is ready once it is over (it works with any executor, eg: tokio)
+pub fn solve_async(base_numbers: &[i32], to_find: i32, approximation: i32) -> SolveFuture {
+    let stop = Arc::new(AtomicBool::new(false));
+    let options = SolveOptions::new()
+        .approximation(approximation)
+        .stop(stop.clone())
+        .build();
+
+    let shared = Arc::new(Mutex::new(Shared::default()));
+    let base_numbers = base_numbers.to_vec();
+    {
+        let shared = shared.clone();
+        std::thread::spawn(move || {
+            let solved = catch_unwind(AssertUnwindSafe(|| {
+                solve_with(&base_numbers, to_find, &options)
+            }))
+            .unwrap_or_else(|_| Err(SolveError::Internal("the search panicked".to_string())));
+
+            if let Ok(mut shared) = shared.lock() {
+                shared.solved = Some(solved);
+                if let Some(waker) = shared.waker.take() {
+                    waker.wake();
+                }
+            }
+        });
+    }
+
+    SolveFuture { shared, stop }
+}
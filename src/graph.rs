@@ -0,0 +1,49 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::MOperation;
+
+// Every way to reach each value with a single operation (see `reachability_graph`)
+// Values are the nodes, and each operation is an edge from its operands to its result:
+// following the operations giving the operands of an operation, and so on,
+// gives every way to reach a value (not only the shortest one)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReachabilityGraph {
+    nodes: BTreeMap<i32, BTreeSet<MOperation>>,
+}
+
+impl ReachabilityGraph {
+    // An input number (None), or an operation giving `value`
+    pub(crate) fn insert(&mut self, value: i32, operation: Option<MOperation>) {
+        let ways = self.nodes.entry(value).or_default();
+        ways.extend(operation);
+    }
+
+    // How many distinct values can be reached
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, value: i32) -> bool {
+        self.nodes.contains_key(&value)
+    }
+
+    // The reachable values, in increasing order
+    pub fn values(&self) -> impl Iterator<Item = i32> + '_ {
+        self.nodes.keys().copied()
+    }
+
+    // The operations giving `value` (none for a value that is not reachable,
+    // or only reachable as an input number)
+    pub fn ways(&self, value: i32) -> impl Iterator<Item = &MOperation> {
+        self.nodes.get(&value).into_iter().flatten()
+    }
+
+    // How many operations (edges) the graph has
+    pub fn edge_count(&self) -> usize {
+        self.nodes.values().map(|x| x.len()).sum()
+    }
+}
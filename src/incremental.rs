@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use crate::{
+    check_tiles, closest_in, keep_shortest, Number, Operation, ResultSet, Rules, SolveError,
+    MAX_TILES,
+};
+
+// A search that grows as numbers are given, eg: as the tiles of a game are revealed
+//
+// Every subset of the numbers keeps the values it reaches when all of its numbers are used.
+// A new number only needs the subsets it is part of: each is split in two parts
+// in every possible way, and the values of both parts are combined.
+// The results are then the same values as `all_combinations` of all the numbers,
+// each with a Number as short as the one it keeps (it may be another calculus of
+// the same length), at the cost of keeping the values of every subset
+pub struct IncrementalSolver {
+    numbers: Vec<i32>,
+    // The values of each subset of the numbers, by its mask (bit i for numbers[i])
+    subsets: Vec<ResultSet>,
+    results: ResultSet,
+}
+
+impl Default for IncrementalSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IncrementalSolver {
+    pub fn new() -> Self {
+        Self {
+            numbers: Vec::new(),
+            // The empty subset reaches nothing
+            subsets: vec![HashMap::new()],
+            results: HashMap::new(),
+        }
+    }
+
+    // The numbers given so far
+    pub fn numbers(&self) -> &[i32] {
+        &self.numbers
+    }
+
+    // The shortest Number of each reachable value (the same as `all_combinations`)
+    pub fn results(&self) -> &ResultSet {
+        &self.results
+    }
+
+    // Same as `solve` with the numbers given so far
+    pub fn solve(&self, to_find: i32, approximation: i32) -> Option<Number> {
+        closest_in(&self.results, to_find, approximation)
+    }
+
+    // Add a number, combining it with what was reachable before
+    // There can be as many numbers as `SolveOptions::max_tiles` allows by default (8)
+    pub fn add_number(&mut self, number: i32) -> Result<(), SolveError> {
+        check_tiles(self.numbers.len() + 1, MAX_TILES)?;
+
+        let bit = 1 << self.numbers.len();
+        self.numbers.push(number);
+
+        // Subsets with the new number, smallest first: their parts are already known
+        for subset in bit..bit << 1 {
+            let values = if subset == bit {
+                HashMap::from([(number, Number::from_int(number))])
+            } else {
+                self.combine(subset)
+            };
+
+            for value in values.values() {
+                keep_shortest(&mut self.results, value.clone());
+            }
+            self.subsets.push(values);
+        }
+
+        Ok(())
+    }
+
+    // The values of `subset`, from every way to split it in two parts
+    fn combine(&self, subset: usize) -> ResultSet {
+        let rules = Rules::default();
+        let mut values = HashMap::new();
+
+        // Every part with its complement, each pair of parts once
+        let mut part = (subset - 1) & subset;
+        while part > 0 {
+            let other = subset ^ part;
+            if part < other {
+                for a in self.subsets[part].values() {
+                    for b in self.subsets[other].values() {
+                        let (a, b) = if a.value <= b.value { (a, b) } else { (b, a) };
+                        combine_pair(a, b, rules, &mut values);
+                    }
+                }
+            }
+            part = (part - 1) & subset;
+        }
+
+        values
+    }
+}
+
+// Same as `combine` for a single pair, keeping one Number of each value
+// All the Numbers of a subset have as many operations: like the search, `keep_shortest`
+// then keeps the one with the lowest operations, whatever order the pairs come in
+fn combine_pair(a: &Number, b: &Number, rules: Rules, values: &mut ResultSet) {
+    let attempts = [
+        (Operation::Addition, a, b),
+        (Operation::Multiplication, a, b),
+        (Operation::Subtraction, a, b),
+        (Operation::Subtraction, b, a),
+        (Operation::Division, a, b),
+        (Operation::Division, b, a),
+    ];
+
+    for (operation, x, y) in attempts {
+        let Ok(value) = rules.apply(operation, x.value, y.value) else {
+            continue;
+        };
+        // Same as `operate`: a value coming back is reached by a smaller subset
+        if rules.prune && (value == a.value || value == b.value) {
+            continue;
+        }
+
+        keep_shortest(values, Number::from(value, operation, x, y));
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+
+// The type of the values of the Numbers (i32 by default)
+// Every operation is checked: None when the result does not fit in the type,
+// or for a division (or remainder) by zero
+// Converting from i32 is used for the numbers given in the options (eg: `SolveOptions::require`)
+pub trait Integer: Copy + Ord + Hash + Debug + Display + Send + Sync + TryFrom<i32> {
+    const ZERO: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+    fn checked_rem(self, rhs: Self) -> Option<Self>;
+
+    // Whether this value is a whole number (always, except for a type of fractions)
+    // Only whole numbers are kept in the results of `all_combinations`
+    #[inline]
+    fn is_integer(self) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                const ZERO: Self = 0;
+
+                #[inline]
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                #[inline]
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+
+                #[inline]
+                fn checked_mul(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_mul(self, rhs)
+                }
+
+                #[inline]
+                fn checked_div(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_div(self, rhs)
+                }
+
+                #[inline]
+                fn checked_rem(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_rem(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
@@ -1,14 +1,65 @@
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender};
 use crossbeam_utils::thread::scope as cross_scope;
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
-use std::thread::available_parallelism;
+use std::collections::btree_map;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{available_parallelism, JoinHandle};
 use std::time::Duration;
 
 // This only affects the `solve` method (not the benchmarks)
 const MAX_WORKERS: usize = 5;
 
-const QUEUE_WAIT: Duration = Duration::from_millis(15);
+// Most numbers combined by default (see `SolveOptions::max_tiles`)
+const MAX_TILES: usize = 8;
+
+// Largest exponent of `Operation::Power`, so its results stay in range of the tiles
+const MAX_EXPONENT: i32 = 10;
+
+mod error;
+mod expression;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "async")]
+mod future;
+mod graph;
+mod incremental;
+mod integer;
+mod number;
+mod options;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod progress;
+mod puzzle;
+mod rational;
+mod solver;
+pub use error::{ParseError, PuzzleError, SolveError, TileError, VerifyError, Violation};
+pub use expression::Expr;
+#[cfg(feature = "ffi")]
+pub use ffi::{deschiffres_free, deschiffres_solve, CSolution};
+#[cfg(feature = "async")]
+pub use future::{solve_async, SolveFuture};
+pub use graph::ReachabilityGraph;
+pub use incremental::IncrementalSolver;
+pub use integer::Integer;
+use number::OpNode;
+pub use number::{ByValue, MOperation, Number, Step};
+use options::Rules;
+pub use options::{SelectionPolicy, SolveOptions};
+#[cfg(feature = "rayon")]
+pub use parallel::all_combinations_rayon;
+use progress::PROGRESS_INTERVAL;
+pub use progress::{Progress, SearchStats};
+pub use puzzle::{
+    difficulty, generate_puzzle, hardest_target, solvable_targets, validate_tiles,
+    validate_tiles_with, Difficulty, Puzzle, RandomSource, SeededRng, TileRules,
+};
+pub use rational::Rational;
+pub use solver::Solver;
 
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "wasm32")] {
@@ -17,89 +68,87 @@ cfg_if::cfg_if! {
     }
 }
 
-#[cfg(feature = "wasm")]
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 #[cfg(feature = "wasm")]
 mod console_log;
 
-type ResultSet = HashMap<i32, Number>;
-
-#[derive(Clone, Copy)]
-#[cfg_attr(feature = "wasm", derive(Serialize, Deserialize))]
-// A materialized operation (a + b) without the result
-pub struct MOperation(pub Operation, pub i32, pub i32);
+type ResultSet<N = i32> = HashMap<N, Number<N>>;
 
-#[derive(Clone)]
-#[cfg_attr(feature = "wasm", derive(Serialize, Deserialize))]
-// Number with the operations that lead to it
-pub struct Number {
-    pub value: i32,
-    pub operations: Vec<MOperation>,
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Operation {
+    #[cfg_attr(feature = "serde", serde(rename = "+"))]
+    Addition,
+    #[cfg_attr(feature = "serde", serde(rename = "*"))]
+    Multiplication,
+    #[cfg_attr(feature = "serde", serde(rename = "-"))]
+    Subtraction,
+    #[cfg_attr(feature = "serde", serde(rename = "/"))]
+    Division,
+    // a ^ b, with an exponent up to 10 (not one of the operations of the game)
+    #[cfg_attr(feature = "serde", serde(rename = "^"))]
+    Power,
+    // The remainder of a / b, for b > 0 (not one of the operations of the game either)
+    #[cfg_attr(feature = "serde", serde(rename = "%"))]
+    Modulo,
+    // The digits of a then those of b, eg: 2 | 5 = 25 (only for input numbers,
+    // see `SolveOptions::concatenation`), written 25 in expressions
+    #[cfg_attr(feature = "serde", serde(rename = "|"))]
+    Concatenation,
 }
 
-impl Number {
-    fn from_int(n: i32) -> Self {
-        Self {
-            value: n,
-            operations: vec![],
-        }
+impl Operation {
+    #[inline]
+    const fn flag(self) -> u8 {
+        1 << self as u8
     }
 
-    fn from(value: i32, op: Operation, a: &Number, b: &Number) -> Self {
-        let operations = [
-            &[MOperation(op, a.value, b.value)][..],
-            &a.operations,
-            &b.operations,
-        ]
-        .concat();
-
-        Self { value, operations }
+    // The result of `a op b` under the default rules of the game, None when it is not allowed:
+    // an overflow, a result that is not strictly positive, or an inexact division
+    // This is what the search uses (see `SolveOptions` for other rules)
+    pub fn apply<N: Integer>(self, a: N, b: N) -> Option<N> {
+        Rules::default().apply(self, a, b).ok()
     }
+}
+
+// A set of allowed operations (the four of the game by default)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OperationSet(u8);
 
-    // The length of a number is how many operations lead to it
-    fn len(&self) -> usize {
-        self.operations.len()
+impl OperationSet {
+    // The four operations of the game: `Operation::Power` and `Operation::Modulo`
+    // are only used when added, eg: OperationSet::ALL.with(Operation::Power)
+    pub const ALL: Self = Self(0b1111);
+    pub const NONE: Self = Self(0);
+
+    pub const fn with(self, op: Operation) -> Self {
+        Self(self.0 | op.flag())
     }
 
-    // A text representation of the calculus that lead to this Number
-    pub fn as_text(self) -> String {
-        let mut output = vec![];
-        for op in self.operations.iter().rev() {
-            let val = match op.0 {
-                Operation::Addition => op.1 + op.2,
-                Operation::Multiplication => op.1 * op.2,
-                Operation::Subtraction => op.1 - op.2,
-                Operation::Division => op.1 / op.2,
-            };
-            let fmt = format!("{} {} {} = {}", op.1, op.0, op.2, val);
-            output.push(fmt);
-        }
+    pub const fn without(self, op: Operation) -> Self {
+        Self(self.0 & !op.flag())
+    }
 
-        output.join("\n")
+    pub const fn contains(self, op: Operation) -> bool {
+        self.0 & op.flag() != 0
     }
 }
 
-// Only show the value
-impl std::fmt::Debug for Number {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.value)
+impl Default for OperationSet {
+    fn default() -> Self {
+        Self::ALL
     }
 }
 
-#[derive(Copy, Clone)]
-#[cfg_attr(feature = "wasm", derive(Serialize, Deserialize))]
-pub enum Operation {
-    #[cfg_attr(feature = "wasm", serde(rename = "+"))]
-    Addition,
-    #[cfg_attr(feature = "wasm", serde(rename = "*"))]
-    Multiplication,
-    #[cfg_attr(feature = "wasm", serde(rename = "-"))]
-    Subtraction,
-    #[cfg_attr(feature = "wasm", serde(rename = "/"))]
-    Division,
+impl FromIterator<Operation> for OperationSet {
+    fn from_iter<T: IntoIterator<Item = Operation>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::NONE, Self::with)
+    }
 }
+
 impl std::fmt::Display for Operation {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
@@ -110,224 +159,514 @@ impl std::fmt::Display for Operation {
                 Operation::Multiplication => "*",
                 Operation::Subtraction => "-",
                 Operation::Division => "/",
+                Operation::Power => "^",
+                Operation::Modulo => "%",
+                Operation::Concatenation => "|",
             }
         )
     }
 }
 
-// Remove a single matching element from a vector of numbers
-//#[inline]
-fn remove_from_vec(vec: &mut Vec<Number>, to_remove: &Number) {
-    for (i, elt) in vec.iter().enumerate() {
-        if elt.value == to_remove.value {
-            vec.remove(i);
-            return;
+// The operation written as `c` (the same as it is displayed)
+impl TryFrom<char> for Operation {
+    type Error = ParseError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '+' => Ok(Operation::Addition),
+            '*' => Ok(Operation::Multiplication),
+            '-' => Ok(Operation::Subtraction),
+            '/' => Ok(Operation::Division),
+            '^' => Ok(Operation::Power),
+            '%' => Ok(Operation::Modulo),
+            '|' => Ok(Operation::Concatenation),
+            _ => Err(ParseError::UnknownOperation(c.to_string())),
+        }
+    }
+}
+
+// Surrounding spaces are ignored, eg: " * ".parse::<Operation>()
+impl std::str::FromStr for Operation {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.trim().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Operation::try_from(c),
+            _ => Err(ParseError::UnknownOperation(s.to_string())),
         }
     }
+}
 
-    panic!("{to_remove:?} was not present in elements")
+// The elements without `a` and `b` themselves
+// With duplicate values, removing the first element of the same value could remove
+// another Number (eg: the input 2 instead of 4 - 2), and its numbers would be used twice
+fn remove_operands<N: Integer>(
+    elements: &[Number<N>],
+    a: &Number<N>,
+    b: &Number<N>,
+) -> Result<Vec<Number<N>>, SolveError> {
+    let subelements: Vec<Number<N>> = elements
+        .iter()
+        .filter(|x| !std::ptr::eq(*x, a) && !std::ptr::eq(*x, b))
+        .cloned()
+        .collect();
+
+    if subelements.len() + 2 != elements.len() {
+        return Err(SolveError::Internal(format!(
+            "{a:?} and {b:?} are not both in elements {elements:?}"
+        )));
+    }
+    Ok(subelements)
 }
 
 // Compute a single operation on 2 numbers (of a given list of numbers)
 // This operation may fail (eg: number less than 0, non-integer division, …)
+// By default every intermediate value must be strictly positive: a subtraction giving 0
+// is rejected too (see `SolveOptions::zero` and `SolveOptions::positive_only`)
 // In case it succeed, remove those 2 from the list and append the result
 // then send this new element list to the "operation" channel
-fn operate(
-    tx: &Sender<Vec<Number>>,
+fn operate<N: Integer>(
+    tx: &Sender<Vec<Number<N>>>,
     operation: Operation,
-    a: &Number,
-    b: &Number,
-    elements: &[Number],
-    rtx: &Sender<Number>,
-) {
+    a: &Number<N>,
+    b: &Number<N>,
+    elements: &[Number<N>],
+    rtx: &Sender<Number<N>>,
+    rules: Rules,
+) -> Result<(), SolveError> {
     let aa = a.value;
     let bb = b.value;
 
-    let value = match operation {
-        Operation::Addition => i32::checked_add(aa, bb),
-        Operation::Multiplication => i32::checked_mul(aa, bb),
-        Operation::Subtraction => {
-            if aa - bb > 0 {
-                Some(aa - bb)
-            } else {
-                None
-            }
-        }
-        Operation::Division => {
-            if bb > 0 && aa % bb == 0 {
-                Some(aa / bb)
-            } else {
-                None
-            }
-        }
-    };
+    let value = rules.apply(operation, aa, bb).ok();
+
+    // Getting one of the operands back (a * 1, a / 1, 2a - a, …) only gives
+    // a longer way to a value that is already there: nothing new can come from it
+    let value = value.filter(|x| !rules.prune || (*x != aa && *x != bb));
 
     if let Some(value) = value {
         let value = Number::from(value, operation, a, b);
-        rtx.send(value.clone()).unwrap();
-
-        if elements.len() > 2 {
-            let mut subelements = elements.to_owned();
-
-            remove_from_vec(&mut subelements, a);
-            remove_from_vec(&mut subelements, b);
+        rtx.send(value.clone())?;
+
+        if rules.reuse || elements.len() > 2 {
+            let mut subelements = match rules.reuse {
+                // Input numbers stay, only results are consumed
+                true => elements
+                    .iter()
+                    .filter(|x| x.len() == 0 || (!std::ptr::eq(*x, a) && !std::ptr::eq(*x, b)))
+                    .cloned()
+                    .collect(),
+                false => remove_operands(elements, a, b)?,
+            };
 
             subelements.push(value);
-            subelements.sort_by(|a, b| a.value.partial_cmp(&b.value).unwrap());
+            subelements.sort_by(|a, b| {
+                a.value
+                    .cmp(&b.value)
+                    .then_with(|| shortest_key(a).cmp(&shortest_key(b)))
+            });
 
-            tx.send(subelements).unwrap();
+            tx.send(subelements)?;
         }
     }
+
+    Ok(())
+}
+
+// Receive from the result channel, and hand every Number to the collector
+fn result_worker<N, F: FnMut(Number<N>)>(rtx: Receiver<Number<N>>, collect: &mut F) {
+    while let Ok(value) = rtx.recv() {
+        collect(value);
+    }
 }
 
-// Receive from the result channel, and set the elements of the result map
+// Set the elements of the result map
 // If an duplicate result was is seen, use the shortest Number (least number of operations)
-fn result_worker(rtx: Receiver<Number>) -> ResultSet {
-    let mut results: ResultSet = HashMap::new();
+// Returns whether `value` was kept (a new value, or a shorter Number)
+fn keep_shortest<N: Integer>(results: &mut ResultSet<N>, value: Number<N>) -> bool {
+    match results.get(&value.value) {
+        Some(current) if shortest_key(current) <= shortest_key(&value) => false,
+        _ => {
+            results.insert(value.value, value);
+            true
+        }
+    }
+}
 
-    while let Ok(value) = rtx.recv() {
-        if let Some(current) = results.get(&value.value) {
-            if current.len() > value.len() {
-                results.insert(value.value, value.clone());
-            }
-        } else {
-            results.insert(value.value, value.clone());
+// Same as `keep_shortest`, choosing with the score (or the selection policy) of `options`
+fn keep_selected<N: Integer>(
+    results: &mut ResultSet<N>,
+    value: Number<N>,
+    options: &SolveOptions<N>,
+) -> bool {
+    if let Some(score) = &options.score {
+        return keep_by(results, value, |x| (score.0)(x));
+    }
+
+    match options.selection {
+        SelectionPolicy::FewestOperations => keep_shortest(results, value),
+        SelectionPolicy::SmallestIntermediates => {
+            keep_by(results, value, |x| (x.max_intermediate(), x.len()))
+        }
+        SelectionPolicy::FewestOperationKinds => {
+            keep_by(results, value, |x| (x.operation_counts().len(), x.len()))
         }
     }
-    results
+}
+
+// Same as `keep_shortest`, keeping the Number with the smallest `key` (then the shortest one)
+fn keep_by<N: Integer, K: Ord>(
+    results: &mut ResultSet<N>,
+    value: Number<N>,
+    key: impl Fn(&Number<N>) -> K,
+) -> bool {
+    match results.get(&value.value) {
+        Some(current)
+            if (key(current), shortest_key(current)) <= (key(&value), shortest_key(&value)) =>
+        {
+            false
+        }
+        _ => {
+            results.insert(value.value, value);
+            true
+        }
+    }
+}
+
+// Numbers with as many operations are ordered by their operations, so the one kept
+// does not depend on the order results arrive in (with multiple workers)
+#[inline]
+fn shortest_key<N: Integer>(number: &Number<N>) -> (usize, Option<&OpNode<N>>) {
+    (number.len(), number.tree())
 }
 
 // Given a list of Number, try to combinate every possible pair of them
+// (only using the allowed operations)
 // Then append those results to the combine channel
-fn combine(tx: Sender<Vec<Number>>, elements: &[Number], rtx: Sender<Number>) {
-    for pair in elements.iter().combinations(2) {
+// Returns how many operations were attempted
+fn combine<N: Integer>(
+    tx: Sender<Vec<Number<N>>>,
+    elements: &[Number<N>],
+    rtx: Sender<Number<N>>,
+    rules: Rules,
+) -> Result<usize, SolveError> {
+    let operations = rules.operations;
+    let addition = operations.contains(Operation::Addition);
+    let multiplication = operations.contains(Operation::Multiplication);
+    let subtraction = operations.contains(Operation::Subtraction);
+    let division = operations.contains(Operation::Division);
+    let power = operations.contains(Operation::Power);
+    let modulo = operations.contains(Operation::Modulo);
+
+    // Pairs of values already combined: with duplicate values in the list,
+    // other pairs of the same values would only give the same results again
+    // Elements are sorted, so the first pair seen uses the shortest Numbers
+    // (with required numbers, the same value may not use the same numbers: all are tried)
+    let mut combined: Vec<(N, N)> = Vec::with_capacity(elements.len() * elements.len());
+
+    let mut attempted = 0;
+    let mut attempt = |operation, a, b| {
+        attempted += 1;
+        operate(&tx, operation, a, b, elements, &rtx, rules)
+    };
+
+    // With reuse, an input number can also be combined with itself
+    let reused = elements
+        .iter()
+        .filter(|x| rules.reuse && x.len() == 0)
+        .map(|x| vec![x, x]);
+
+    for pair in elements.iter().combinations(2).chain(reused) {
         if let [a, b] = pair[..] {
-            operate(&tx, Operation::Addition, a, b, elements, &rtx);
-            operate(&tx, Operation::Multiplication, a, b, elements, &rtx);
-            operate(&tx, Operation::Subtraction, a, b, elements, &rtx);
-            operate(&tx, Operation::Subtraction, b, a, elements, &rtx);
-            operate(&tx, Operation::Division, a, b, elements, &rtx);
-            operate(&tx, Operation::Division, b, a, elements, &rtx);
+            if !rules.required && combined.contains(&(a.value, b.value)) {
+                continue;
+            }
+            combined.push((a.value, b.value));
+
+            // Addition and multiplication are commutative (b + a is never tried),
+            // a - b and b - a (or a / b and b / a) are the same when a == b
+            let same = a.value == b.value;
+
+            if addition {
+                attempt(Operation::Addition, a, b)?;
+            }
+            if multiplication {
+                attempt(Operation::Multiplication, a, b)?;
+            }
+            if subtraction {
+                attempt(Operation::Subtraction, a, b)?;
+                if !same {
+                    attempt(Operation::Subtraction, b, a)?;
+                }
+            }
+            if division {
+                attempt(Operation::Division, a, b)?;
+                if !same {
+                    attempt(Operation::Division, b, a)?;
+                }
+            }
+            if power {
+                attempt(Operation::Power, a, b)?;
+                if !same {
+                    attempt(Operation::Power, b, a)?;
+                }
+            }
+            if modulo {
+                attempt(Operation::Modulo, a, b)?;
+                if !same {
+                    attempt(Operation::Modulo, b, a)?;
+                }
+            }
+            // Only input numbers are concatenated
+            if rules.concatenation && a.len() == 0 && b.len() == 0 {
+                attempt(Operation::Concatenation, a, b)?;
+                if !same {
+                    attempt(Operation::Concatenation, b, a)?;
+                }
+            }
         }
     }
+
+    Ok(attempted)
 }
 
 // Listen the combination channel for new lists of Numbers, and combine them
-// (that will probably generate more combination events)
-fn combination_worker(
-    tx: Sender<Vec<Number>>,
-    rx: Receiver<Vec<Number>>,
-    result_tx: Sender<Number>,
-) {
-    loop {
-        while let Ok(elements) = rx.recv_timeout(QUEUE_WAIT) {
-            combine(tx.clone(), &elements, result_tx.clone());
+// (until every list of the current level was combined)
+fn combination_worker<N: Integer>(
+    tx: Sender<Vec<Number<N>>>,
+    rx: Receiver<Vec<Number<N>>>,
+    result_tx: Sender<Number<N>>,
+    rules: Rules,
+    stop: Option<&AtomicBool>,
+) -> Result<usize, SolveError> {
+    let mut attempted = 0;
+    for elements in rx {
+        // Once stopped, only drain the channel
+        if is_stopped(stop) {
+            continue;
         }
+        attempted += combine(tx.clone(), &elements, result_tx.clone(), rules)?;
+    }
 
-        if tx.is_empty() {
-            break;
+    Ok(attempted)
+}
+
+// Lists of Numbers, by their values
+type Sieve<N> = HashMap<SieveKey<N>, Vec<Number<N>>>;
+
+// Most values of an inline sieve key (the default `SolveOptions::max_tiles`)
+const INLINE_KEY: usize = MAX_TILES;
+
+// The values of a list of Numbers (sorted, like the list)
+// Short lists are copied to an array, so making a key does not allocate:
+// a Vec is only used for longer lists
+pub(crate) enum SieveKey<N> {
+    // The number of values, then the values (padded with zeros)
+    Inline(u8, [N; INLINE_KEY]),
+    Heap(Vec<N>),
+    // With required numbers (see `SolveOptions::require`): the values, each with the
+    // required numbers it uses (sorted). Lists with the same values that used different
+    // required numbers do not lead to the same solutions, so both have to be kept
+    Required(Vec<(N, Vec<N>)>),
+}
+
+impl<N: Integer> SieveKey<N> {
+    pub(crate) fn new(elements: &[Number<N>]) -> Self {
+        if elements.len() > INLINE_KEY {
+            return SieveKey::Heap(elements.iter().map(|x| x.value).collect());
         }
+
+        let mut values = [N::ZERO; INLINE_KEY];
+        for (value, element) in values.iter_mut().zip(elements) {
+            *value = element.value;
+        }
+        SieveKey::Inline(elements.len() as u8, values)
     }
-}
 
-// Single thread/worker that recieve the combinaisons
-// and only forwards them if they weren't already seen
-fn combine_sieve(rx: Receiver<Vec<Number>>, tx: Sender<Vec<Number>>) {
-    let mut seen = HashSet::with_capacity(500);
+    // Same as `new`, telling apart the lists that used different `required` numbers
+    pub(crate) fn with_required(elements: &[Number<N>], required: &[N]) -> Self {
+        if required.is_empty() {
+            return Self::new(elements);
+        }
 
-    loop {
-        while let Ok(elements) = rx.recv_timeout(QUEUE_WAIT) {
-            // Map elements to integers
-            let values: Vec<i32> = elements.iter().map(|x| x.value).collect();
+        let mut values: Vec<(N, Vec<N>)> = elements
+            .iter()
+            .map(|x| (x.value, required_used(x, required)))
+            .collect();
+        values.sort_unstable();
+        SieveKey::Required(values)
+    }
 
-            // HashSet.insert returns true if element was NOT present
-            if seen.insert(values) {
-                tx.send(elements).unwrap()
-            }
+    // Without the padding
+    #[inline]
+    fn values(&self) -> &[N] {
+        match self {
+            SieveKey::Inline(len, values) => &values[..*len as usize],
+            SieveKey::Heap(values) => values,
+            SieveKey::Required(_) => &[],
         }
-        if tx.is_empty() {
-            break;
+    }
+}
+
+impl<N: Integer> PartialEq for SieveKey<N> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SieveKey::Required(values), SieveKey::Required(other)) => values == other,
+            (SieveKey::Required(_), _) | (_, SieveKey::Required(_)) => false,
+            _ => self.values() == other.values(),
         }
     }
 }
 
-#[inline]
-fn results_append(rx: &Receiver<Number>, results: &mut HashMap<i32, Number>) {
-    while let Ok(value) = rx.try_recv() {
-        if let Some(current) = results.get(&value.value) {
-            if current.len() > value.len() {
-                results.insert(value.value, value.clone());
+impl<N: Integer> Eq for SieveKey<N> {}
+
+impl<N: Integer> Hash for SieveKey<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            SieveKey::Required(values) => values.hash(state),
+            _ => self.values().hash(state),
+        }
+    }
+}
+
+// Only keep a single list of Numbers for a given list of values
+// When the same values are seen again, keep the list with the "smallest" operations,
+// so the one kept does not depend on the order lists arrive in
+fn sieve_insert<N: Integer>(sieve: &mut Sieve<N>, elements: Vec<Number<N>>, required: &[N]) {
+    match sieve.entry(SieveKey::with_required(&elements, required)) {
+        Entry::Occupied(mut entry) => {
+            if is_smaller(&elements, entry.get()) {
+                entry.insert(elements);
             }
-        } else {
-            results.insert(value.value, value.clone());
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(elements);
         }
     }
 }
 
-fn threadless_worker(
-    tx: Sender<Vec<Number>>,
-    rx: Receiver<Vec<Number>>,
-    result_tx: Sender<Number>,
-    result_rx: Receiver<Number>,
-) -> ResultSet {
-    let mut seen = HashSet::with_capacity(500);
-    let mut results: HashMap<i32, Number> = HashMap::with_capacity(500);
+// Order of two lists of Numbers with the same values
+#[inline]
+fn is_smaller<N: Integer>(elements: &[Number<N>], other: &[Number<N>]) -> bool {
+    elements
+        .iter()
+        .map(shortest_key)
+        .lt(other.iter().map(shortest_key))
+}
 
-    loop {
-        results_append(&result_rx, &mut results);
+// Single thread/worker that recieve the combinaisons of a level
+// and only keeps one of each (see `sieve_insert`)
+fn combine_sieve<N: Integer>(rx: Receiver<Vec<Number<N>>>, required: &[N]) -> Sieve<N> {
+    let mut sieve = HashMap::with_capacity(500);
 
-        let elements = match rx.try_recv() {
-            Ok(x) => x,
-            Err(_) => break,
-        };
+    for elements in rx {
+        sieve_insert(&mut sieve, elements, required);
+    }
+    sieve
+}
 
-        // Do not combine again if this set of elements was already seen
-        let mut values: Vec<i32> = elements.iter().map(|x| x.value).collect();
-        values.sort();
+#[inline]
+fn results_append<N, F: FnMut(Number<N>)>(rx: &Receiver<Number<N>>, collect: &mut F) {
+    while let Ok(value) = rx.try_recv() {
+        collect(value);
+    }
+}
 
-        // HashSet.insert returns true if element was already present
-        if !seen.insert(values) {
-            continue;
+// Combine every list of Numbers of a level, and return the lists of the next one
+// (with the operations attempted and the channel depth in `stats`)
+fn threadless_worker<N: Integer, F: FnMut(Number<N>)>(
+    level: Vec<Vec<Number<N>>>,
+    options: &SolveOptions<N>,
+    collect: &mut F,
+    stats: &mut SearchStats,
+) -> Result<Vec<Vec<Number<N>>>, SolveError> {
+    let rules = options.rules();
+    let stop = options.stop.as_deref();
+    let required = required_numbers(options).unwrap_or_default();
+
+    let (tx, rx) = unbounded();
+    let (result_tx, result_rx) = unbounded();
+    let mut sieve = HashMap::with_capacity(500);
+
+    for elements in level {
+        if is_stopped(stop) {
+            break;
         }
+        stats.attempted += combine(tx.clone(), &elements, result_tx.clone(), rules)?;
 
-        combine(tx.clone(), &elements, result_tx.clone());
-    }
-    results
-}
+        let depth = rx.len().max(result_rx.len());
+        stats.peak_channel_depth = stats.peak_channel_depth.max(depth);
 
-// Main algorithm, find all combinations for a given list of integers
-// Use workers + channels for multithreading
-pub fn all_combinations(base_numbers: &[i32], max_workers: usize) -> ResultSet {
-    let ncores = match available_parallelism() {
-        Ok(x) => x.get(),
-        Err(_) => 1,
-    };
+        results_append(&result_rx, collect);
+        while let Ok(elements) = rx.try_recv() {
+            sieve_insert(&mut sieve, elements, &required);
+        }
+    }
 
-    let nworkers = match ncores {
-        0 | 1 | 2 => 1,
-        n => std::cmp::min(n - 2, max_workers),
-    };
+    Ok(sieve.into_values().collect())
+}
 
-    let (combine_tx, combine_rx) = unbounded();
-    let (sieve_tx, sieve_rx) = unbounded();
-    let (result_tx, result_rx) = unbounded();
+// Whether there are numbers to combine, but not too many of them
+fn check_tiles(count: usize, max: usize) -> Result<(), SolveError> {
+    match count {
+        0 => Err(SolveError::EmptyInput),
+        count if count > max => Err(SolveError::TooManyTiles { count, max }),
+        _ => Ok(()),
+    }
+}
 
-    // Initial list of numbers
-    let initial = base_numbers.iter().map(|x| Number::from_int(*x)).collect();
-    combine_tx.send(initial).unwrap();
+#[inline]
+fn is_stopped(stop: Option<&AtomicBool>) -> bool {
+    stop.is_some_and(|x| x.load(Ordering::Relaxed))
+}
 
-    if cfg!(target_arch = "wasm32") || nworkers < 2 {
-        return threadless_worker(combine_tx, combine_rx, result_tx, result_rx);
+// An unbounded channel, or a bounded one when a capacity is given
+fn channel<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    match capacity {
+        Some(capacity) => bounded(capacity),
+        None => unbounded(),
     }
+}
+
+// Same as `threadless_worker`, using workers + channels
+// (with bounded channels if the options ask for it)
+fn threaded_worker<N: Integer, F: FnMut(Number<N>)>(
+    level: Vec<Vec<Number<N>>>,
+    nworkers: usize,
+    options: &SolveOptions<N>,
+    collect: &mut F,
+    stats: &mut SearchStats,
+) -> Result<Vec<Vec<Number<N>>>, SolveError> {
+    let rules = options.rules();
+    let capacity = options.channel_capacity;
+    let stop = options.stop.as_deref();
+    let required = required_numbers(options).unwrap_or_default();
+
+    let (combine_tx, combine_rx) = channel(capacity);
+    let (sieve_tx, sieve_rx) = channel(capacity);
+    let (result_tx, result_rx) = channel(capacity);
+
+    let panicked = |_| SolveError::Internal("a worker panicked".to_string());
 
-    // WARNING: the current implementation is bugged
-    // Since the sieve / combien threads are feeding each other,
-    // there is no way of reliably know when they are both finished (that is still performant)
-    // In some cases the workers are too slow to fill the channels and the worker exits early
     cross_scope(|scope| {
         let mut workers = Vec::new();
 
+        // Feed the level to the workers from its own thread, since sending may block
+        // Workers are done once every list of the level was received (and combine_tx dropped)
+        let feeder = scope.spawn(move |_| {
+            for elements in level {
+                if is_stopped(stop) {
+                    break;
+                }
+                combine_tx.send(elements)?;
+            }
+            Ok(0)
+        });
+        workers.push(feeder);
+
         // Combinaison workers (ncores - 2)
+        // They block when the sieve or result channels are full,
+        // but those are always consumed (by the sieve and this thread)
         for _ in 0..nworkers {
             let result_tx = result_tx.clone();
 
@@ -335,76 +674,2676 @@ pub fn all_combinations(base_numbers: &[i32], max_workers: usize) -> ResultSet {
             let tx = sieve_tx.clone();
             let rx = combine_rx.clone();
 
-            let worker = scope.spawn(|_| combination_worker(tx, rx, result_tx));
+            let worker = scope.spawn(move |_| combination_worker(tx, rx, result_tx, rules, stop));
             workers.push(worker);
         }
         drop(result_tx);
+        drop(sieve_tx);
 
         // Sieve worker
+        let sieve_depth = sieve_rx.clone();
+        let sieve = scope.spawn(|_| combine_sieve(sieve_rx, &required));
+
+        // Channel depths are sampled as results arrive
+        let result_depth = result_rx.clone();
+        result_worker(result_rx, &mut |value| {
+            let depth = [combine_rx.len(), sieve_depth.len(), result_depth.len()];
+            stats.peak_channel_depth = stats
+                .peak_channel_depth
+                .max(depth[0])
+                .max(depth[1])
+                .max(depth[2]);
+            collect(value);
+        });
+
+        // Workers should have finished by the time result_worker is done
+        for worker in workers {
+            stats.attempted += worker.join().map_err(panicked)??;
+        }
+        let sieve = sieve.join().map_err(panicked)?;
+
+        Ok(sieve.into_values().collect())
+    })
+    .map_err(panicked)?
+}
+
+// Main algorithm, go through all combinations for a given list of integers
+// and feed every computed Number to `collect`
+//
+// The search goes level by level: all lists of Numbers of a level have the same length,
+// and combining them gives the (shorter) lists of the next level.
+// Waiting for a level to be over before starting the next one means every worker
+// knows when to stop, and the sieve has seen every list when choosing which one to keep
+fn search<N: Integer, F: FnMut(Number<N>)>(
+    base_numbers: &[N],
+    options: &SolveOptions<N>,
+    collect: F,
+) -> Result<SearchStats, SolveError> {
+    search_with_progress(base_numbers, options, collect, |_| {})
+}
+
+// Same as `search`, calling `progress` from this thread after each level
+// and every PROGRESS_INTERVAL computed Numbers
+fn search_with_progress<N: Integer, F: FnMut(Number<N>), P: FnMut(&Progress)>(
+    base_numbers: &[N],
+    options: &SolveOptions<N>,
+    collect: F,
+    progress: P,
+) -> Result<SearchStats, SolveError> {
+    let ncores = match available_parallelism() {
+        Ok(x) => x.get(),
+        Err(_) => 1,
+    };
+
+    let nworkers = match ncores {
+        0..=2 => 1,
+        n => std::cmp::min(n - 2, options.max_workers),
+    };
+
+    search_levels(base_numbers, nworkers, options, collect, progress)
+}
+
+// Same as `search_with_progress`, with exactly `nworkers` combination workers
+// (whatever the number of cores available)
+fn search_levels<N: Integer, F: FnMut(Number<N>), P: FnMut(&Progress)>(
+    base_numbers: &[N],
+    nworkers: usize,
+    options: &SolveOptions<N>,
+    mut collect: F,
+    mut progress: P,
+) -> Result<SearchStats, SolveError> {
+    check_tiles(base_numbers.len(), options.max_tiles)?;
+
+    // Initial list of numbers
+    let initial: Vec<Number<N>> = base_numbers.iter().map(|x| Number::from_int(*x)).collect();
+
+    // Each number is a (trivial) result by itself, without any operation
+    for number in &initial {
+        collect(number.clone());
+    }
+
+    let mut counters = Progress {
+        results: base_numbers.len(),
+        ..Default::default()
+    };
+    let mut stats = SearchStats::default();
+
+    let mut level = vec![initial];
+    while !level.is_empty() {
+        // Each level adds an operation to the Numbers it makes
+        if options
+            .max_operations
+            .is_some_and(|max| counters.level >= max)
         {
-            let sieve_rx = sieve_rx.clone();
-            let combine_tx = combine_tx.clone();
-            let worker = scope.spawn(|_| combine_sieve(sieve_rx, combine_tx));
-            workers.push(worker)
+            break;
         }
+        let combined = level.len();
 
-        // No need? Workers should have finished by the time result_worker is done
-        //for worker in workers {
-        //    worker.join().unwrap();
-        //}
+        let mut count = |value| {
+            counters.results += 1;
+            collect(value);
 
-        result_worker(result_rx)
-    })
-    .unwrap()
+            if counters.results.is_multiple_of(PROGRESS_INTERVAL) {
+                progress(&counters);
+            }
+        };
+
+        level = if cfg!(target_arch = "wasm32") || nworkers < 2 {
+            threadless_worker(level, options, &mut count, &mut stats)?
+        } else {
+            threaded_worker(level, nworkers, options, &mut count, &mut stats)?
+        };
+
+        counters.level += 1;
+        counters.combined += combined;
+        counters.states += level.len();
+        progress(&counters);
+
+        // Keep whatever was found so far
+        if is_stopped(options.stop.as_deref()) {
+            break;
+        }
+        // With reuse, lists do not get shorter: stop at the operations a Number
+        // could have without it
+        if options.reuse && counters.level + 1 >= base_numbers.len() {
+            break;
+        }
+    }
+
+    stats.succeeded = counters.results - base_numbers.len();
+    stats.states = counters.states;
+    Ok(stats)
 }
 
-pub fn solve(base_numbers: &[i32], to_find: i32, approximation: i32) -> Option<Number> {
-    let results = all_combinations(base_numbers, MAX_WORKERS);
-    // println!("Found {} possible combinations", results.len());
+// Find all combinations for a given list of integers (of any Integer type, eg: i32 or i64),
+// only keeping the shortest Number for each reachable value
+pub fn all_combinations<N: Integer>(
+    base_numbers: &[N],
+    max_workers: usize,
+) -> Result<ResultSet<N>, SolveError> {
+    let options = SolveOptions::new().max_workers(max_workers).build();
+    all_combinations_with(base_numbers, &options)
+}
+
+// Same as `all_combinations`, configured by `options` (the approximation is ignored)
+// Only whole values are kept, even when fractions are allowed
+pub fn all_combinations_with<N: Integer>(
+    base_numbers: &[N],
+    options: &SolveOptions<N>,
+) -> Result<ResultSet<N>, SolveError> {
+    all_combinations_with_progress(base_numbers, options, |_| {})
+}
+
+// Same as `all_combinations_with`, calling `progress` regularly with the search counters
+// The callback is always called from the calling thread (never from a worker):
+// after each level of the search (one per operation), and every 10 000 computed Numbers
+pub fn all_combinations_with_progress<N: Integer, P: FnMut(&Progress)>(
+    base_numbers: &[N],
+    options: &SolveOptions<N>,
+    progress: P,
+) -> Result<ResultSet<N>, SolveError> {
+    let (results, _) = combinations_with_stats(base_numbers, options, progress)?;
+    Ok(results)
+}
+
+// Same as `all_combinations`, with the counters of the whole search
+pub fn all_combinations_stats<N: Integer>(
+    base_numbers: &[N],
+    max_workers: usize,
+) -> Result<(ResultSet<N>, SearchStats), SolveError> {
+    let options = SolveOptions::new().max_workers(max_workers).build();
+    combinations_with_stats(base_numbers, &options, |_| {})
+}
+
+// An upper bound on the lists of numbers a search of `tile_count` numbers goes through
+// (`SearchStats::states`), from arithmetic only: eg: to turn down inputs that would take too long
+// A list of m numbers has m (m - 1) / 2 pairs, each tried with 6 operations (a + b, a * b,
+// a - b, b - a, a / b, b / a), so it makes at most 3 m (m - 1) lists of m - 1 numbers.
+// From n numbers, level k (k operations) then has at most
+//   L(k) = 3 n (n - 1) × 3 (n - 1) (n - 2) × … × 3 (n - k + 1) (n - k)
+// lists, and the bound is L(1) + L(2) + … + L(n - 1) (saturating at u128::MAX)
+// It is not what the search really goes through: operations breaking the rules are
+// dropped, and lists with the same values are only kept once, which leaves far fewer
+// (23 386 of 24 694 290 for [5, 25, 2, 50, 100, 10])
+// Other operations, reuse or concatenation are not counted
+pub fn estimate_combinations(tile_count: usize) -> u128 {
+    let mut total: u128 = 0;
+    let mut lists: u128 = 1;
+    for m in (2..=tile_count as u128).rev() {
+        lists = lists.saturating_mul(3 * m * (m - 1));
+        total = total.saturating_add(lists);
+    }
+    total
+}
+
+fn combinations_with_stats<N: Integer, P: FnMut(&Progress)>(
+    base_numbers: &[N],
+    options: &SolveOptions<N>,
+    progress: P,
+) -> Result<(ResultSet<N>, SearchStats), SolveError> {
+    // A required number the type can not hold can not be used: nothing is kept
+    let required = required_numbers(options);
+
+    let mut results: ResultSet<N> = HashMap::with_capacity(500);
+    let stats = search_with_progress(
+        base_numbers,
+        options,
+        |value| {
+            let used = (!options.use_all || value.numbers_used() == base_numbers.len())
+                && required.as_ref().is_some_and(|x| uses_all_of(&value, x));
+            if used && value.value.is_integer() {
+                keep_selected(&mut results, value, options);
+            }
+        },
+        progress,
+    )?;
+
+    Ok((results, stats))
+}
+
+// Run the search on a new thread, sending every computed Number to the returned channel
+// as soon as it is found: the input numbers first, then many Numbers for most values
+// (not only the shortest ones, nor only whole values), in no particular order
+// The channel is bounded when `options` ask for it (the search then waits for the receiver)
+//
+// The channel is disconnected (iterating it ends) once the search is over: after every
+// Number was sent, or when it stopped (see `SolveOptions::stop`) or failed.
+// The handle tells which, with the search counters; dropping the receiver stops the search
+pub fn all_combinations_channel<N: Integer + 'static>(
+    base_numbers: &[N],
+    options: &SolveOptions<N>,
+) -> (
+    Receiver<Number<N>>,
+    JoinHandle<Result<SearchStats, SolveError>>,
+) {
+    let (tx, rx) = channel(options.channel_capacity);
+
+    let mut options = options.clone();
+    let stop = options.stop.get_or_insert_with(Default::default).clone();
+    let base_numbers = base_numbers.to_vec();
+
+    let handle = std::thread::spawn(move || {
+        search(&base_numbers, &options, |value| {
+            if tx.send(value).is_err() {
+                stop.store(true, Ordering::Relaxed);
+            }
+        })
+    });
+
+    (rx, handle)
+}
+
+// Same as `all_combinations`, calling `on_result` every time a Number is kept:
+// for a new value, or a better way to an already found one (shorter, see `keep_shortest`)
+// The callback is called from the thread collecting the results (the calling one),
+// which holds up the search meanwhile: it should be fast
+pub fn all_combinations_streaming<N: Integer, F: FnMut(&Number<N>)>(
+    base_numbers: &[N],
+    max_workers: usize,
+    mut on_result: F,
+) -> Result<ResultSet<N>, SolveError> {
+    let options = SolveOptions::new().max_workers(max_workers).build();
+
+    let mut results: ResultSet<N> = HashMap::with_capacity(500);
+    search(base_numbers, &options, |value| {
+        if value.value.is_integer() && keep_shortest(&mut results, value.clone()) {
+            on_result(&value);
+        }
+    })?;
+
+    Ok(results)
+}
+
+// The required numbers of `options` (see `SolveOptions::require`),
+// None if the type can not hold one of them
+fn required_numbers<N: Integer>(options: &SolveOptions<N>) -> Option<Vec<N>> {
+    options
+        .required
+        .iter()
+        .map(|x| N::try_from(*x).ok())
+        .collect()
+}
 
-    for i in 0..approximation + 1 {
-        if let Some(result) = results.get(&(to_find + i)) {
-            return Some(result.to_owned());
-        } else if let Some(result) = results.get(&(to_find - i)) {
-            return Some(result.to_owned());
+// The input numbers of `number` that are in `required` (sorted)
+// A number required once is only counted once, even if `number` uses it twice
+fn required_used<N: Integer>(number: &Number<N>, required: &[N]) -> Vec<N> {
+    let mut left = required.to_vec();
+    let mut used = vec![];
+    for tile in number.tiles() {
+        if let Some(i) = left.iter().position(|x| *x == tile) {
+            left.swap_remove(i);
+            used.push(tile);
         }
     }
+    used.sort_unstable();
+    used
+}
+
+// Whether every number of `required` is an input number of `number`
+// (a number given twice must be used twice)
+fn uses_all_of<N: Integer>(number: &Number<N>, required: &[N]) -> bool {
+    if required.is_empty() {
+        return true;
+    }
 
-    None
+    let mut tiles = number.tiles();
+    required
+        .iter()
+        .all(|x| match tiles.iter().position(|y| y == x) {
+            Some(i) => {
+                tiles.swap_remove(i);
+                true
+            }
+            None => false,
+        })
 }
 
-#[cfg(feature = "wasm")]
-#[wasm_bindgen]
-pub fn solve_js(base_numbers: &[i32], to_find: i32, approximation: i32) -> JsValue {
-    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+// How to rank Numbers when looking for `to_find`:
+// closest value first, then the shortest one (and lowest value for determinism)
+#[inline]
+fn closest_key(number: &Number, to_find: i32) -> (u32, usize, i32) {
+    (number.value.abs_diff(to_find), number.len(), number.value)
+}
 
-    let solved = solve(base_numbers, to_find, approximation);
+// The Number reaching `to_find`, or the closest one up to `approximation` away
+// (then the shortest one). Any subset of the numbers may be used: every intermediate
+// value of the search is a result, whatever numbers are left, and the shortest Number
+// is also the one using the fewest numbers (see `Number::numbers_used`)
+pub fn solve(
+    base_numbers: &[i32],
+    to_find: i32,
+    approximation: i32,
+) -> Result<Option<Number>, SolveError> {
+    let options = SolveOptions::new().approximation(approximation).build();
+    solve_with(base_numbers, to_find, &options)
+}
 
-    serde_wasm_bindgen::to_value(&solved).unwrap()
+// The exact solution with the smallest largest intermediate value (`Number::max_intermediate`),
+// then the fewest operations: the easiest one to compute mentally
+// Same as `solve_with` and `SelectionPolicy::SmallestIntermediates`
+pub fn solve_smoothest(base_numbers: &[i32], to_find: i32) -> Result<Option<Number>, SolveError> {
+    let options = SolveOptions::new()
+        .selection(SelectionPolicy::SmallestIntermediates)
+        .build();
+    solve_with(base_numbers, to_find, &options)
 }
 
-#[cfg(test)]
-mod test {
-    use crate::*;
+// Same as `solve`, with control over the number of combination workers
+// 0 (or 1) means a single threaded search, which is also what is used
+// when there are not enough cores available
+pub fn solve_with_workers(
+    base_numbers: &[i32],
+    to_find: i32,
+    approximation: i32,
+    max_workers: usize,
+) -> Result<Option<Number>, SolveError> {
+    let options = SolveOptions::new()
+        .approximation(approximation)
+        .max_workers(max_workers)
+        .build();
+    solve_with(base_numbers, to_find, &options)
+}
 
-    #[test]
-    fn test_combinations_multi() {
-        let numbers = vec![5, 25, 2, 50, 10];
+pub fn solve_with(
+    base_numbers: &[i32],
+    to_find: i32,
+    options: &SolveOptions,
+) -> Result<Option<Number>, SolveError> {
+    let results = all_combinations_with(base_numbers, options)?;
+    // println!("Found {} possible combinations", results.len());
 
-        let combinations = all_combinations(&numbers, 4);
+    Ok(closest_in(&results, to_find, options.approximation))
+}
 
-        assert_eq!(combinations.len(), 1085);
-        assert!(combinations.contains_key(&280));
+// Same as `solve` for each of the `targets`, from a single search
+pub fn solve_many(
+    base_numbers: &[i32],
+    targets: &[i32],
+    approximation: i32,
+) -> Result<HashMap<i32, Option<Number>>, SolveError> {
+    let results = all_combinations(base_numbers, MAX_WORKERS)?;
+
+    Ok(targets
+        .iter()
+        .map(|x| (*x, closest_in(&results, *x, approximation)))
+        .collect())
+}
+
+// The best result up to `approximation` away from `to_find`
+fn closest_in(results: &ResultSet, to_find: i32, approximation: i32) -> Option<Number> {
+    // Every result in the approximation band (a negative approximation accepts nothing)
+    let approximation = u32::try_from(approximation).ok()?;
+    results
+        .values()
+        .filter(|x| x.value.abs_diff(to_find) <= approximation)
+        .min_by_key(|x| closest_key(x, to_find))
+        .cloned()
+}
+
+// Outcome of a solve with a deadline
+#[derive(Clone, Debug)]
+pub enum TimedResult {
+    // The search went through every combination: None means there is no solution
+    Complete(Option<Number>),
+    // The deadline passed before the search was over, with the best result found until then
+    // (None only means that nothing was found in time)
+    TimedOut(Option<Number>),
+}
+
+// Same as `solve`, stopping the search once `timeout` is over
+pub fn solve_with_timeout(
+    base_numbers: &[i32],
+    to_find: i32,
+    approximation: i32,
+    timeout: Duration,
+) -> Result<TimedResult, SolveError> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let options = SolveOptions::new()
+        .approximation(approximation)
+        .stop(stop.clone())
+        .build();
+
+    // Timer thread: wakes up either at the deadline, or when the solve is over
+    let (done_tx, done_rx) = bounded::<()>(0);
+    let timer = {
+        let stop = stop.clone();
+        std::thread::spawn(move || {
+            if done_rx.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+                stop.store(true, Ordering::Relaxed);
+            }
+        })
+    };
+
+    let solved = solve_with(base_numbers, to_find, &options);
+    drop(done_tx);
+    timer
+        .join()
+        .map_err(|_| SolveError::Internal("the timer panicked".to_string()))?;
+
+    // The deadline may also have passed right as the search was completing
+    if stop.load(Ordering::Relaxed) {
+        Ok(TimedResult::TimedOut(solved?))
+    } else {
+        Ok(TimedResult::Complete(solved?))
+    }
+}
+
+// The reachable Number closest to `to_find`, with its signed distance (value - to_find)
+// The distance is an i64, as it does not always fit in an i32 (eg: 1 - i32::MIN)
+// When two values are as close (one above, one below), use the shortest one
+pub fn solve_closest(
+    base_numbers: &[i32],
+    to_find: i32,
+) -> Result<Option<(Number, i64)>, SolveError> {
+    let results = all_combinations(base_numbers, MAX_WORKERS)?;
+
+    let closest = results
+        .into_values()
+        .min_by_key(|x| closest_key(x, to_find))
+        .map(|x| {
+            let distance = i64::from(x.value) - i64::from(to_find);
+            (x, distance)
+        });
+
+    Ok(closest)
+}
+
+// How many distinct values can be reached (the same as `all_combinations(...).len()`)
+// Only values are combined, without building any Number (nor keeping their operations),
+// in a single thread: much less memory for when only the coverage of a set matters
+pub fn count_reachable(base_numbers: &[i32]) -> Result<usize, SolveError> {
+    check_tiles(base_numbers.len(), MAX_TILES)?;
+
+    let rules = Rules::default();
+    let mut reached: HashSet<i32> = base_numbers.iter().copied().collect();
+
+    let mut initial = base_numbers.to_vec();
+    initial.sort_unstable();
+    let mut level = HashSet::from([initial]);
+
+    while !level.is_empty() {
+        let mut next = HashSet::with_capacity(level.len());
+
+        for values in level {
+            for (i, j) in (0..values.len()).tuple_combinations() {
+                let (a, b) = (values[i], values[j]);
+                // Same as `combine`: with sorted values, a pair seen before is the previous one
+                if (i > 0 && values[i - 1] == a) || (j > i + 1 && values[j - 1] == b) {
+                    continue;
+                }
+
+                let attempts = [
+                    (Operation::Addition, a, b),
+                    (Operation::Multiplication, a, b),
+                    (Operation::Subtraction, a, b),
+                    (Operation::Subtraction, b, a),
+                    (Operation::Division, a, b),
+                    (Operation::Division, b, a),
+                ];
+                for (operation, x, y) in attempts {
+                    let Ok(value) = rules.apply(operation, x, y) else {
+                        continue;
+                    };
+                    if rules.prune && (value == a || value == b) {
+                        continue;
+                    }
+
+                    reached.insert(value);
+                    if values.len() > 2 {
+                        let mut subvalues = values.clone();
+                        subvalues.remove(j);
+                        subvalues.remove(i);
+                        subvalues.push(value);
+                        subvalues.sort_unstable();
+                        next.insert(subvalues);
+                    }
+                }
+            }
+        }
+
+        level = next;
     }
 
-    #[test]
-    fn test_combinations_single() {
-        let numbers = vec![5, 25, 2, 50, 10];
+    Ok(reached.len())
+}
+
+// Every operation the search makes, as a graph of the reachable values
+// Unlike `all_combinations`, nothing is left out: a value can have many ways to reach it
+// This keeps every distinct operation, which is much more than the results:
+// 57142 operations for the 11864 values reachable with [5, 25, 2, 50, 100, 10]
+pub fn reachability_graph(base_numbers: &[i32]) -> Result<ReachabilityGraph, SolveError> {
+    let mut graph = ReachabilityGraph::default();
+    search(base_numbers, &SolveOptions::default(), |value| {
+        graph.insert(value.value, value.tree().map(|x| x.operation));
+    })?;
+
+    Ok(graph)
+}
 
-        let combinations = all_combinations(&numbers, 0);
+// Whether `to_find` can be reached exactly, without keeping any result
+// The search is stopped as soon as `to_find` is found: this is only faster
+// for a reachable value, otherwise every combination still has to be tried
+pub fn is_solvable(base_numbers: &[i32], to_find: i32) -> Result<bool, SolveError> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let options = SolveOptions::new().stop(stop.clone()).build();
 
-        assert_eq!(combinations.len(), 1085);
-        assert!(combinations.contains_key(&280));
+    search(base_numbers, &options, |value| {
+        if value.value == to_find {
+            stop.store(true, Ordering::Relaxed);
+        }
+    })?;
+
+    Ok(stop.load(Ordering::Relaxed))
+}
+
+// An exact solution, stopping the search as soon as one is found (None if there is none)
+// Unlike `solve`, which goes through every combination, this returns the first Number
+// reaching `to_find`. The search makes Numbers one operation at a time (a level of the
+// search for each), so it always has the fewest operations a solution can have,
+// as many as the one `solve` returns: among solutions of that length, it is whichever
+// path found it first, which may change from one search to the next with several workers.
+// Much faster for a reachable value, otherwise every combination still has to be tried
+// (like `is_solvable`). The lists of numbers of the levels gone through are still kept
+pub fn solve_fast(base_numbers: &[i32], to_find: i32) -> Result<Option<Number>, SolveError> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let options = SolveOptions::new().stop(stop.clone()).build();
+
+    let mut found = None;
+    search(base_numbers, &options, |value| {
+        if found.is_none() && value.value == to_find {
+            found = Some(value);
+            stop.store(true, Ordering::Relaxed);
+        }
+    })?;
+
+    Ok(found)
+}
+
+// Every distinct Number that reach exactly `to_find`, shortest first
+//
+// The same calculus is usually found from several intermediate lists of values,
+// possibly with its operations in a different order: only the first one with a given
+// `canonical_key` is kept.
+// The search never explores twice the same list of intermediate values,
+// so two solutions that only differ by how an intermediate value was obtained
+// (eg: 6 as 2 * 3 or 1 + 5) are only returned once
+pub fn solve_all(base_numbers: &[i32], to_find: i32) -> Result<Vec<Number>, SolveError> {
+    let mut results = vec![];
+    search(base_numbers, &SolveOptions::default(), |value| {
+        if value.value == to_find {
+            results.push(value);
+        }
+    })?;
+
+    results.sort_by(|a, b| shortest_key(a).cmp(&shortest_key(b)));
+
+    let mut seen = HashSet::new();
+    results.retain(|x| seen.insert(x.canonical_key()));
+
+    Ok(results)
+}
+
+// Up to `k` distinct Numbers reaching exactly `to_find` (see `solve_all`), the shortest ones:
+// sorted by number of operations then `canonical_key` (as `Number::cmp_by_complexity`)
+// Only the k best are kept during the search, and the same ones are returned
+// whatever the order Numbers are found in
+pub fn solve_k_shortest(
+    base_numbers: &[i32],
+    to_find: i32,
+    k: usize,
+) -> Result<Vec<Number>, SolveError> {
+    let mut kept: BTreeMap<(usize, String), Number> = BTreeMap::new();
+    search(base_numbers, &SolveOptions::default(), |value| {
+        if value.value != to_find || k == 0 {
+            return;
+        }
+
+        match kept.entry((value.len(), value.canonical_key())) {
+            // The same calculus: keep the smallest tree
+            btree_map::Entry::Occupied(mut entry) => {
+                if value.tree() < entry.get().tree() {
+                    entry.insert(value);
+                }
+            }
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(value);
+                if kept.len() > k {
+                    kept.pop_last();
+                }
+            }
+        }
+    })?;
+
+    Ok(kept.into_values().collect())
+}
+
+// How many distinct calculus (see `Number::canonical_key`) lead to each reachable value
+//
+// Every computed Number is considered, not only the shortest one, but the search
+// still explores each list of intermediate values once (see `solve_all`).
+// A hash of each calculus is kept until the end: up to about 130 000 of them
+// (a few MB) for 6 numbers, expect this to grow quickly with more numbers
+pub fn reachable_histogram(base_numbers: &[i32]) -> Result<BTreeMap<i32, usize>, SolveError> {
+    let mut seen: HashMap<i32, HashSet<u64>> = HashMap::new();
+    search(base_numbers, &SolveOptions::default(), |value| {
+        let mut hasher = DefaultHasher::new();
+        value.canonical_key().hash(&mut hasher);
+        seen.entry(value.value).or_default().insert(hasher.finish());
+    })?;
+
+    Ok(seen.into_iter().map(|(k, v)| (k, v.len())).collect())
+}
+
+// How many distinct calculus reach exactly `to_find`
+// With `canonical`, calculus that only differ by the order of commutative operands or
+// how associative chains are nested count once (see `Number::canonical_key`, the same
+// as `solve_all(...).len()`), otherwise each different written expression counts
+// (eg: (2 + 3) + 5 and 2 + (3 + 5)).
+// Like `solve_all`, every computed Number is considered, but each list of intermediate
+// values is only explored once
+pub fn solution_count(
+    base_numbers: &[i32],
+    to_find: i32,
+    canonical: bool,
+) -> Result<usize, SolveError> {
+    let mut seen = HashSet::new();
+    search(base_numbers, &SolveOptions::default(), |value| {
+        if value.value == to_find {
+            let key = match canonical {
+                true => value.canonical_key(),
+                false => value.as_expression(),
+            };
+            seen.insert(key);
+        }
+    })?;
+
+    Ok(seen.len())
+}
+
+// The value of an infix expression (eg: "2 * (50 + 25)") under the rules of the game:
+// only positive integers, and exact divisions
+// This is the inverse of `Number::as_expression`, to check a solution
+pub fn evaluate(expr: &str) -> Result<i32, ParseError> {
+    Expr::parse(expr)?.evaluate(&Rules::default())
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn solve_js(
+    base_numbers: &[i32],
+    to_find: i32,
+    approximation: i32,
+) -> Result<JsValue, JsError> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let solved = solve(base_numbers, to_find, approximation)?;
+
+    Ok(serde_wasm_bindgen::to_value(&solved)?)
+}
+
+// A solution for JavaScript, with typed getters (unlike the plain objects of `solve_js`)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub struct WasmSolution {
+    number: Number,
+    to_find: i32,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl WasmSolution {
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> i32 {
+        self.number.value
+    }
+
+    // One line per step, eg: ["50 + 25 = 75", "2 * 75 = 150"]
+    #[wasm_bindgen(getter)]
+    pub fn steps(&self) -> Vec<String> {
+        self.number.steps().iter().map(Step::to_string).collect()
+    }
+
+    // Same as `Number::as_expression`, eg: "(2 * (50 + 25))"
+    #[wasm_bindgen(getter)]
+    pub fn expression(&self) -> String {
+        self.number.as_expression()
+    }
+
+    // Whether the value is the target (otherwise it is only the closest one)
+    #[wasm_bindgen(getter, js_name = isExact)]
+    pub fn is_exact(&self) -> bool {
+        self.number.value == self.to_find
+    }
+}
+
+// Same as `solve_js`, with a `WasmSolution` (undefined when there is no solution)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn solve_js_typed(
+    base_numbers: &[i32],
+    to_find: i32,
+    approximation: i32,
+) -> Result<Option<WasmSolution>, JsError> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let solved = solve(base_numbers, to_find, approximation)?;
+
+    Ok(solved.map(|number| WasmSolution { number, to_find }))
+}
+
+// Same as `solve_js`, calling `callback` with the search counters (see `Progress`),
+// eg: {level: 2, combined: 53, states: 903, results: 1711}
+// It is called after each level of the search (one per operation, so 5 for 6 numbers)
+// and every 10 000 computed Numbers: 10 to 20 times in all for 6 numbers
+// What the callback returns (or throws) is ignored
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn solve_js_with_progress(
+    base_numbers: &[i32],
+    to_find: i32,
+    approximation: i32,
+    callback: &js_sys::Function,
+) -> Result<JsValue, JsError> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let options = SolveOptions::new().approximation(approximation).build();
+    let results = all_combinations_with_progress(base_numbers, &options, |progress| {
+        if let Ok(progress) = serde_wasm_bindgen::to_value(progress) {
+            let _ = callback.call1(&JsValue::NULL, &progress);
+        }
+    })?;
+    let solved = closest_in(&results, to_find, approximation);
+
+    Ok(serde_wasm_bindgen::to_value(&solved)?)
+}
+
+// Every reachable value (sorted), eg: to show what can be made with the numbers
+// (the search is single threaded in wasm, like for every other function)
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn reachable_values_js(base_numbers: &[i32]) -> Result<JsValue, JsError> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let results = all_combinations(base_numbers, MAX_WORKERS)?;
+    let values: Vec<i32> = results.into_keys().sorted_unstable().collect();
+
+    Ok(serde_wasm_bindgen::to_value(&values)?)
+}
+
+// Most solutions returned by `solve_all_js` at once
+#[cfg(feature = "wasm")]
+const SOLUTIONS_PAGE: usize = 50;
+
+// A page of `solve_all`: up to 50 solutions (shortest first), from the `offset`th one
+// A page shorter than 50 is the last one
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub fn solve_all_js(base_numbers: &[i32], to_find: i32, offset: usize) -> Result<JsValue, JsError> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let solutions = solve_all(base_numbers, to_find)?;
+    let page: Vec<Number> = solutions
+        .into_iter()
+        .skip(offset)
+        .take(SOLUTIONS_PAGE)
+        .collect();
+
+    Ok(serde_wasm_bindgen::to_value(&page)?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_combinations_multi() {
+        let numbers = vec![5, 25, 2, 50, 10];
+
+        let combinations = all_combinations(&numbers, 4).unwrap();
+
+        assert_eq!(combinations.len(), 1085);
+        assert!(combinations.contains_key(&280));
+    }
+
+    #[test]
+    fn test_combinations_i64() {
+        // 100 000 * 100 000 * 1 000 does not fit in an i32
+        let numbers: Vec<i64> = vec![100_000, 100_000, 1_000];
+        let combinations = all_combinations(&numbers, 4).unwrap();
+        assert!(combinations.contains_key(&10_000_000_000_000));
+
+        let numbers = vec![100_000, 100_000, 1_000];
+        let overflowing = all_combinations(&numbers, 4).unwrap();
+        assert!(overflowing.len() < combinations.len());
+    }
+
+    #[test]
+    fn test_rational_new() {
+        let fraction = |x, y| Rational::new(x, y).map(|x| (x.numerator(), x.denominator()));
+
+        assert_eq!(fraction(6, -4), Some((-3, 2)));
+        assert_eq!(fraction(1, 0), None);
+        assert_eq!(fraction(i64::MIN, 1), Some((i64::MIN, 1)));
+        assert_eq!(fraction(i64::MIN, i64::MIN), Some((1, 1)));
+        assert_eq!(fraction(0, i64::MIN), Some((0, 1)));
+        assert_eq!(fraction(i64::MIN, 2), Some((i64::MIN / 2, 1)));
+        // 1/2^63 and 2^63/1 do not fit
+        assert_eq!(fraction(1, i64::MIN), None);
+        assert_eq!(fraction(i64::MIN, -1), None);
+    }
+
+    #[test]
+    fn test_combinations_fractions() {
+        let numbers: Vec<Rational> = [3, 7, 25, 50].into_iter().map(Rational::from).collect();
+        let target = Rational::from(136);
+
+        let classic = all_combinations(&numbers, 4).unwrap();
+        assert!(!classic.contains_key(&target));
+
+        let options = SolveOptions::new().fractions(true).build();
+        let combinations = all_combinations_with(&numbers, &options).unwrap();
+        assert!(combinations.keys().all(|x| x.is_integer()));
+        assert_eq!(combinations.len(), 201);
+        assert_eq!(
+            combinations[&target].to_string(),
+            "7 / 25 = 7/25\n3 - 7/25 = 68/25\n68/25 * 50 = 136"
+        );
+
+        // Without a type of fractions, this is the same as the classic rules
+        let numbers = vec![3, 7, 25, 50];
+        let options = SolveOptions::new().fractions(true).build();
+        let all = all_combinations_with(&numbers, &options).unwrap();
+        assert_eq!(all.len(), classic.len());
+    }
+
+    #[test]
+    fn test_solve_negatives() {
+        let numbers = vec![2, 3, 10];
+
+        let solved = solve(&numbers, -8, 0).unwrap();
+        assert!(solved.is_none());
+
+        let options = SolveOptions::new().negatives(true).build();
+        let solved = solve_with(&numbers, -8, &options).unwrap().unwrap();
+        assert_eq!(solved.as_expression(), "(2 - 10)");
+
+        let combinations = all_combinations_with(&numbers, &options).unwrap();
+        assert_eq!(combinations[&-28].as_expression(), "(2 - (3 * 10))");
+    }
+
+    #[test]
+    fn test_solve_reuse() {
+        let numbers = [2, 3, 100];
+        // At most 100 * 3 * 2 = 600 without reuse
+        assert!(solve(&numbers, 10000, 0).unwrap().is_none());
+
+        let options = SolveOptions::new().allow_reuse(true).build();
+        let result = solve_with(&numbers, 10000, &options).unwrap().unwrap();
+        assert_eq!(result.tiles(), vec![100, 100]);
+        assert!(!result.uses_valid_tiles(&numbers));
+
+        // (2 + 3) * 2, 2 is used again after a result was made from it
+        let result = solve_with(&numbers, 10, &options).unwrap().unwrap();
+        assert_eq!(result.len(), 2);
+
+        // No more operations than without reuse
+        let results = all_combinations_with(&numbers, &options).unwrap();
+        assert!(results.values().all(|x| x.len() <= 2));
+        for number in all_combinations(&numbers, 0).unwrap().keys() {
+            assert!(results.contains_key(number));
+        }
+    }
+
+    #[test]
+    fn test_solve_positive_only() {
+        let options = SolveOptions::new().positive_only(false).build();
+
+        let solved = solve_with(&[3, 5, 5], 0, &options).unwrap().unwrap();
+        assert_eq!(solved.as_expression(), "(5 - 5)");
+
+        // 10 / (2 - 3), a division by a negative number
+        let combinations = all_combinations_with(&[2, 3, 10], &options).unwrap();
+        assert!(combinations.contains_key(&-10));
+        assert!(combinations[&-10].verify().is_err());
+        assert_eq!(combinations[&-10].verify_with(&options), Ok(()));
+
+        let negatives = SolveOptions::new().negatives(true).build();
+        let others = all_combinations_with(&[2, 3, 10], &negatives).unwrap();
+        assert_eq!(others.len(), combinations.len());
+    }
+
+    #[test]
+    fn test_combinations_use_all() {
+        let options = SolveOptions::new().use_all(true).build();
+
+        let results = all_combinations_with(&[2, 1], &options).unwrap();
+        assert_eq!(
+            results.keys().sorted().collect::<Vec<_>>(),
+            vec![&1, &2, &3]
+        );
+        assert_eq!(results[&2].as_expression(), "(2 * 1)");
+
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let results = all_combinations_with(&numbers, &options).unwrap();
+        assert!(results.values().all(|x| x.numbers_used() == numbers.len()));
+        assert!(results.contains_key(&281));
+    }
+
+    #[test]
+    fn test_solve_require() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let result = solve(&numbers, 250, 0).unwrap().unwrap();
+        assert_eq!(result.tiles(), vec![5, 50]);
+
+        let options = SolveOptions::new().require(&[100]).build();
+        let result = solve_with(&numbers, 250, &options).unwrap().unwrap();
+        assert!(result.tiles().contains(&100));
+        assert_eq!(result.len(), 2);
+
+        let results = all_combinations_with(&numbers, &options).unwrap();
+        assert!(results.values().all(|x| x.tiles().contains(&100)));
+
+        // There is a single 2
+        let options = SolveOptions::new().require(&[2, 2]).build();
+        assert!(all_combinations_with(&numbers, &options)
+            .unwrap()
+            .is_empty());
+
+        // [50, 5] is reached both by 10 * 5 then 9 - 4, and by 9 - 4 then 10 * 5:
+        // only the second one leads to 10 * (9 - 4) with the 4
+        let options = SolveOptions::new().require(&[4]).build();
+        let result = solve_with(&[10, 5, 9, 4], 50, &options).unwrap().unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.tiles().contains(&4));
+        for workers in [0, 4] {
+            let mut found = false;
+            search_levels(
+                &[10, 5, 9, 4],
+                workers,
+                &options,
+                |x| {
+                    found |= x.value == 50 && x.len() == 2 && x.tiles().contains(&4);
+                },
+                |_| {},
+            )
+            .unwrap();
+            assert!(found);
+        }
+        let options = SolveOptions::new().require(&[8]).build();
+        let result = solve_with(&[2, 10, 9, 8], 90, &options).unwrap().unwrap();
+        assert!(result.tiles().contains(&8));
+    }
+
+    #[test]
+    fn test_max_operations() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let options = SolveOptions::new().max_operations(2).build();
+
+        let results = all_combinations_with(&numbers, &options).unwrap();
+        assert!(results.values().all(|x| x.len() <= 2));
+        assert!(results.len() < all_combinations(&numbers, 0).unwrap().len());
+
+        let result = solve_with(&numbers, 252, &options).unwrap().unwrap();
+        assert_eq!(result.len(), 2);
+        // A single solution, with 5 operations
+        assert!(solve_with(&numbers, 281, &options).unwrap().is_none());
+
+        let options = SolveOptions::new().max_operations(0).build();
+        let results = all_combinations_with(&numbers, &options).unwrap();
+        assert_eq!(results.len(), numbers.len());
+    }
+
+    #[test]
+    fn test_solve_selection() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let result = solve(&numbers, 195, 0).unwrap().unwrap();
+        assert_eq!(result.as_infix(), "2 * 100 - 5");
+        assert_eq!(result.max_intermediate(), 200);
+
+        let options = SolveOptions::new()
+            .selection(SelectionPolicy::SmallestIntermediates)
+            .build();
+        let result = solve_with(&numbers, 195, &options).unwrap().unwrap();
+        assert_eq!(result.value, 195);
+        assert_eq!(result.max_intermediate(), 195);
+        assert_eq!(result.len(), 3);
+
+        let numbers = [2, 8, 10, 100];
+        let result = solve(&numbers, 82, 0).unwrap().unwrap();
+        assert_eq!(result.as_infix(), "2 + 8 * 10");
+
+        let options = SolveOptions::new()
+            .selection(SelectionPolicy::FewestOperationKinds)
+            .build();
+        let result = solve_with(&numbers, 82, &options).unwrap().unwrap();
+        assert_eq!(result.as_infix(), "100 - 10 - 8");
+
+        // 50 - 25 + 12 then 2 + 10 + 25: the additions only win, with as many operations
+        let (n2, n10, n12, n25, n50) = (
+            Number::from_int(2),
+            Number::from_int(10),
+            Number::from_int(12),
+            Number::from_int(25),
+            Number::from_int(50),
+        );
+        let mixed = Number::from(25, Operation::Subtraction, &n50, &n25);
+        let mixed = Number::from(37, Operation::Addition, &mixed, &n12);
+        let additions = Number::from(12, Operation::Addition, &n2, &n10);
+        let additions = Number::from(37, Operation::Addition, &additions, &n25);
+
+        let mut results = ResultSet::new();
+        assert!(keep_selected(&mut results, mixed, &options));
+        assert!(keep_selected(&mut results, additions.clone(), &options));
+        assert_eq!(results[&37].operations(), additions.operations());
+    }
+
+    #[test]
+    fn test_solve_score_with() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+
+        let options = SolveOptions::new()
+            .score_with(|x| x.max_intermediate() as i64)
+            .build();
+        let result = solve_with(&numbers, 164, &options).unwrap().unwrap();
+        let smoothest = solve_smoothest(&numbers, 164).unwrap().unwrap();
+        assert_eq!(result.operations(), smoothest.operations());
+
+        // The default
+        let options = SolveOptions::new().score_with(|x| x.len() as i64).build();
+        let results = all_combinations_with(&numbers, &options).unwrap();
+        for (value, number) in all_combinations(&numbers, 0).unwrap() {
+            assert_eq!(results[&value].operations(), number.operations());
+        }
+
+        // As few divisions as possible
+        let divisions = |x: &Number| {
+            let operations = x.operations();
+            operations
+                .iter()
+                .filter(|x| x.0 == Operation::Division)
+                .count() as i64
+        };
+        let result = solve(&numbers, 131, 0).unwrap().unwrap();
+        assert_eq!(divisions(&result), 2);
+        let options = SolveOptions::new().score_with(divisions).build();
+        let result = solve_with(&numbers, 131, &options).unwrap().unwrap();
+        assert_eq!(divisions(&result), 1);
+
+        // Any type of numbers can be scored
+        let divisions = |x: &Number<i64>| x.operation_counts().get(&Operation::Division).copied();
+        let numbers = numbers.map(i64::from);
+        let options = SolveOptions::new()
+            .score_with(move |x| divisions(x).unwrap_or(0) as i64)
+            .build();
+        let results = all_combinations_with(&numbers, &options).unwrap();
+        assert_eq!(divisions(&results[&131]), Some(1));
+    }
+
+    #[test]
+    fn test_solve_smoothest() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let result = solve(&numbers, 164, 0).unwrap().unwrap();
+        assert_eq!(result.max_intermediate(), 4050);
+
+        let result = solve_smoothest(&numbers, 164).unwrap().unwrap();
+        assert_eq!(result.value, 164);
+        assert_eq!(result.max_intermediate(), 164);
+        assert!(solve_smoothest(&[2, 3], 100).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_solve_zero() {
+        let numbers = vec![3, 5, 5];
+
+        let solved = solve(&numbers, 0, 0).unwrap();
+        assert!(solved.is_none());
+
+        let options = SolveOptions::new().zero(true).build();
+        let solved = solve_with(&numbers, 0, &options).unwrap().unwrap();
+        assert_eq!(solved.as_expression(), "(5 - 5)");
+
+        // Still no negative value
+        let combinations = all_combinations_with(&numbers, &options).unwrap();
+        assert!(combinations.keys().all(|x| *x >= 0));
+    }
+
+    #[test]
+    fn test_combinations_single() {
+        let numbers = vec![5, 25, 2, 50, 10];
+
+        let combinations = all_combinations(&numbers, 0).unwrap();
+
+        assert_eq!(combinations.len(), 1085);
+        assert!(combinations.contains_key(&280));
+    }
+
+    #[test]
+    fn test_combinations_deterministic() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        let sorted = |results: ResultSet| {
+            let mut results: Vec<(i32, Vec<MOperation>)> = results
+                .into_values()
+                .map(|x| (x.value, x.operations()))
+                .collect();
+            results.sort();
+            results
+        };
+
+        let single = sorted(all_combinations(&numbers, 0).unwrap());
+        for _ in 0..10 {
+            let multi = sorted(all_combinations(&numbers, 4).unwrap());
+            assert!(multi == single);
+        }
+    }
+
+    #[test]
+    fn test_combinations_progress() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+        let mut calls: Vec<Progress> = vec![];
+
+        let combinations =
+            all_combinations_with_progress(&numbers, &SolveOptions::default(), |x| calls.push(*x))
+                .unwrap();
+
+        let last = calls.last().unwrap();
+        assert_eq!(combinations.len(), 11864);
+        assert_eq!(last.level, 5);
+        assert!(last.results > combinations.len());
+        assert!(last.states > 0 && last.combined > last.states);
+        // Called during levels as well
+        assert!(calls.len() > 5);
+    }
+
+    #[test]
+    fn test_combinations_stop() {
+        let numbers = vec![5, 25, 2, 50, 10, 8, 4];
+        let stop = Arc::new(AtomicBool::new(false));
+        let options = SolveOptions::new().stop(stop.clone()).build();
+
+        let handle = std::thread::spawn(move || all_combinations_with(&numbers, &options));
+
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let start = std::time::Instant::now();
+        stop.store(true, Ordering::Relaxed);
+
+        let combinations = handle.join().unwrap().unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_millis(500));
+        assert!(!combinations.is_empty() && combinations.len() < 51834);
+    }
+
+    #[test]
+    fn test_solve_with_timeout() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+        let result = solve_with_timeout(&numbers, 281, 0, Duration::from_secs(60)).unwrap();
+        assert!(matches!(result, TimedResult::Complete(Some(x)) if x.value == 281));
+
+        let numbers = vec![5, 25, 2, 50, 10, 8, 4];
+        let start = std::time::Instant::now();
+        let result = solve_with_timeout(&numbers, 999_999, 0, Duration::from_millis(50)).unwrap();
+        assert!(matches!(result, TimedResult::TimedOut(None)));
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_combinations_prune() {
+        let numbers = vec![1, 3, 7, 10, 25, 50];
+
+        let mut pruned = Progress::default();
+        let options = SolveOptions::new().build();
+        let combinations =
+            all_combinations_with_progress(&numbers, &options, |x| pruned = *x).unwrap();
+
+        let mut exhaustive = Progress::default();
+        let options = SolveOptions::new().prune(false).build();
+        let all = all_combinations_with_progress(&numbers, &options, |x| exhaustive = *x).unwrap();
+
+        assert!(pruned.states < exhaustive.states);
+        assert!(pruned.results < exhaustive.results);
+
+        assert_eq!(combinations.len(), all.len());
+        for (value, number) in &all {
+            assert_eq!(combinations[value].len(), number.len());
+        }
+    }
+
+    #[test]
+    fn test_combinations_stats() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        let mut last = Progress::default();
+        let options = SolveOptions::new().max_workers(1).build();
+        let combinations =
+            all_combinations_with_progress(&numbers, &options, |x| last = *x).unwrap();
+
+        for workers in [1, 4] {
+            let (results, stats) = all_combinations_stats(&numbers, workers).unwrap();
+            assert_eq!(results.len(), combinations.len());
+
+            assert_eq!(stats.succeeded, last.results - numbers.len());
+            assert_eq!(stats.states, last.states);
+            assert!(stats.attempted > stats.succeeded);
+            assert!((stats.states as u128) < estimate_combinations(numbers.len()));
+        }
+    }
+
+    #[test]
+    fn test_estimate_combinations() {
+        assert_eq!(estimate_combinations(0), 0);
+        assert_eq!(estimate_combinations(1), 0);
+        // 6 lists of a single number
+        assert_eq!(estimate_combinations(2), 6);
+        // 18 lists of 2 numbers, each making 6 lists of 1
+        assert_eq!(estimate_combinations(3), 18 + 18 * 6);
+        assert!(estimate_combinations(7) > estimate_combinations(6));
+        assert_eq!(estimate_combinations(100), u128::MAX);
+    }
+
+    #[test]
+    fn test_combinations_streaming() {
+        let numbers = [1, 3, 7, 10, 25, 50];
+        let mut streamed = HashMap::new();
+        let mut calls = 0;
+
+        let results = all_combinations_streaming(&numbers, MAX_WORKERS, |x| {
+            calls += 1;
+            // Only new values, or shorter Numbers (ties are broken by their operations)
+            if let Some((len, _)) = streamed.get(&x.value) {
+                assert!(x.len() <= *len);
+            }
+            streamed.insert(x.value, (x.len(), x.as_expression()));
+        })
+        .unwrap();
+
+        assert!(calls >= results.len());
+        // The last Number streamed for a value is the one kept
+        assert_eq!(streamed.len(), results.len());
+        for (value, number) in results {
+            assert_eq!(streamed[&value], (number.len(), number.as_expression()));
+        }
+    }
+
+    #[test]
+    fn test_sieve_key() {
+        let numbers = |values: &[i32]| values.iter().map(|x| Number::from_int(*x)).collect_vec();
+
+        // Padding does not make [3] the same as [3, 0]
+        let short = SieveKey::new(&numbers(&[3]));
+        assert!(short == SieveKey::new(&numbers(&[3])));
+        assert!(short != SieveKey::new(&numbers(&[3, 0])));
+
+        // Too many values for an inline key
+        let long = SieveKey::new(&numbers(&[1, 2, 3, 4, 5, 6, 7, 8, 9]));
+        assert!(matches!(long, SieveKey::Heap(_)));
+        assert!(long == SieveKey::new(&numbers(&[1, 2, 3, 4, 5, 6, 7, 8, 9])));
+    }
+
+    #[test]
+    fn test_combinations_channel() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let (rx, handle) = all_combinations_channel(&numbers, &SolveOptions::default());
+
+        let mut results = HashMap::new();
+        let mut received = 0;
+        for value in rx {
+            received += 1;
+            keep_shortest(&mut results, value);
+        }
+        let stats = handle.join().unwrap().unwrap();
+
+        assert_eq!(received, numbers.len() + stats.succeeded);
+        assert_eq!(results.len(), all_combinations(&numbers, 0).unwrap().len());
+
+        // Only take a few: the search stops once the receiver is dropped
+        let (rx, handle) = all_combinations_channel(&numbers, &SolveOptions::default());
+        assert_eq!(rx.iter().take(10).count(), 10);
+        drop(rx);
+        assert!(handle.join().unwrap().unwrap().succeeded < stats.succeeded);
+
+        let (rx, handle) = all_combinations_channel::<i32>(&[], &SolveOptions::default());
+        assert_eq!(rx.iter().count(), 0);
+        assert_eq!(handle.join().unwrap(), Err(SolveError::EmptyInput));
+    }
+
+    #[test]
+    fn test_combinations_duplicate_tiles() {
+        let numbers = [2, 2, 3, 3, 25, 100];
+        let threadless = combinations_with_workers(&numbers, 1);
+        let threaded = combinations_with_workers(&numbers, 4);
+        assert_eq!(threadless.len(), threaded.len());
+
+        // No input number is used more times than it is given
+        for results in [threadless, threaded] {
+            for number in results.values() {
+                let mut tiles = numbers.to_vec();
+                for tile in number.tiles() {
+                    let i = tiles.iter().position(|x| *x == tile).unwrap();
+                    tiles.swap_remove(i);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_combinations_duplicates() {
+        let mut last = Progress::default();
+        let combinations =
+            all_combinations_with_progress(&[2, 2], &SolveOptions::default(), |x| last = *x)
+                .unwrap();
+
+        // 2 + 2, 2 * 2, 2 / 2 (and the 2 tiles)
+        assert_eq!(last.results, 5);
+        assert_eq!(combinations.len(), 3);
+    }
+
+    #[test]
+    fn test_reachable_histogram() {
+        let numbers = vec![2, 3, 4];
+        let histogram = reachable_histogram(&numbers).unwrap();
+
+        let combinations = all_combinations(&numbers, 1).unwrap();
+        assert!(combinations.keys().sorted().eq(histogram.keys()));
+        assert!(histogram.values().all(|x| *x > 0));
+
+        // 2 * 3 * 4, whatever the order
+        assert_eq!(histogram[&24], 1);
+        // 2 + 4, 2 * 3, 3 * (4 - 2)
+        assert_eq!(histogram[&6], 3);
+    }
+
+    #[test]
+    fn test_solve_k_shortest() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+        let solutions = solve_k_shortest(&numbers, 997, 3).unwrap();
+        assert_eq!(solutions.len(), 3);
+        assert!(solutions.windows(2).all(|x| x[0].len() <= x[1].len()));
+        assert!(solutions.iter().all(|x| x.value == 997));
+
+        let shortest = solve(&numbers, 997, 0).unwrap().unwrap();
+        assert_eq!(solutions[0].len(), shortest.len());
+
+        // Every one of them, in the same order as the first 3
+        let all = solve_k_shortest(&numbers, 997, 100).unwrap();
+        assert_eq!(all.len(), solve_all(&numbers, 997).unwrap().len());
+        for (a, b) in all.iter().zip(&solutions) {
+            assert_eq!(a.operations(), b.operations());
+        }
+
+        assert!(solve_k_shortest(&numbers, 997, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_solution_count() {
+        let numbers = vec![2, 3, 4];
+        assert_eq!(solution_count(&numbers, 24, true).unwrap(), 1);
+        // 4 * (2 * 3), 3 * (2 * 4), 2 * (3 * 4)
+        assert_eq!(solution_count(&numbers, 24, false).unwrap(), 3);
+        assert_eq!(solution_count(&numbers, 100, false).unwrap(), 0);
+
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+        let solutions = solve_all(&numbers, 997).unwrap();
+        assert_eq!(
+            solution_count(&numbers, 997, true).unwrap(),
+            solutions.len()
+        );
+    }
+
+    #[test]
+    fn test_count_reachable() {
+        for numbers in [
+            vec![2, 2],
+            vec![5, 25, 2, 50, 100, 10],
+            vec![1, 3, 7, 10, 25, 50],
+            vec![3, 3, 3, 7, 7, 100],
+        ] {
+            let expected = all_combinations(&numbers, MAX_WORKERS).unwrap().len();
+            assert_eq!(count_reachable(&numbers).unwrap(), expected);
+        }
+        assert_eq!(count_reachable(&[]), Err(SolveError::EmptyInput));
+    }
+
+    #[test]
+    fn test_reachability_graph() {
+        let graph = reachability_graph(&[2, 3, 5]).unwrap();
+        let results = all_combinations(&[2, 3, 5], MAX_WORKERS).unwrap();
+        assert_eq!(graph.len(), results.len());
+
+        use Operation::*;
+        let ways: Vec<_> = graph.ways(10).copied().collect();
+        assert_eq!(
+            ways,
+            vec![
+                MOperation(Addition, 2, 8),
+                MOperation(Addition, 3, 7),
+                MOperation(Addition, 5, 5),
+                MOperation(Multiplication, 2, 5),
+            ]
+        );
+        // (2 + 3) + 5: 5 is both an input number and reachable
+        assert!(graph.ways(5).eq(&[MOperation(Addition, 2, 3)]));
+        assert_eq!(graph.ways(12).count(), 0);
+        assert!(graph.contains(2) && !graph.contains(12));
+    }
+
+    #[test]
+    fn test_is_solvable() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        assert_eq!(is_solvable(&numbers, 281), Ok(true));
+        assert_eq!(is_solvable(&numbers, 25), Ok(true));
+        assert_eq!(is_solvable(&[1, 1, 1], 4), Ok(false));
+        assert_eq!(is_solvable(&[], 4), Err(SolveError::EmptyInput));
+    }
+
+    #[test]
+    fn test_solve_fast() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        for to_find in [25, 250, 252, 997, 281, 952] {
+            let result = solve_fast(&numbers, to_find).unwrap();
+            let shortest = solve(&numbers, to_find, 0).unwrap();
+            assert_eq!(result.is_some(), shortest.is_some());
+            if let Some(result) = result {
+                assert_eq!(result.value, to_find);
+                assert_eq!(result.verify(), Ok(()));
+                assert!(result.uses_valid_tiles(&numbers));
+                assert_eq!(result.len(), shortest.unwrap().len());
+
+                // The fewest operations of any solution
+                let solutions = solve_all(&numbers, to_find).unwrap();
+                let fewest = solutions.iter().map(Number::len).min();
+                assert_eq!(Some(result.len()), fewest, "{to_find}");
+            }
+        }
+
+        assert_eq!(solve_fast(&numbers, 25).unwrap().unwrap().len(), 0);
+        assert!(solve_fast(&numbers, 831).unwrap().is_none());
+        assert_eq!(solve_fast(&[], 4).unwrap_err(), SolveError::EmptyInput);
+    }
+
+    #[test]
+    fn test_generate_puzzle() {
+        let puzzle = generate_puzzle(&mut SeededRng::new(42), 2, 4).unwrap();
+
+        assert_eq!(puzzle.numbers.len(), 6);
+        assert!(puzzle.numbers[..2]
+            .iter()
+            .all(|x| [25, 50, 75, 100].contains(x)));
+        assert!(puzzle.numbers[2..].iter().all(|x| (1..=10).contains(x)));
+        assert!((100..=999).contains(&puzzle.target));
+        assert_eq!(puzzle.solution.value, puzzle.target);
+        assert_eq!(puzzle.solution.verify(), Ok(()));
+
+        // Reproducible, with the reference SplitMix64 numbers
+        assert_eq!(SeededRng::new(0).next_u64(), 0xe220a8397b1dcdaf);
+        let again = generate_puzzle(&mut SeededRng::new(42), 2, 4).unwrap();
+        assert_eq!(again.numbers, puzzle.numbers);
+        assert_eq!(again.target, puzzle.target);
+        assert_eq!(
+            again.solution.as_expression(),
+            puzzle.solution.as_expression()
+        );
+
+        let mut rng = SeededRng::new(42);
+        assert_eq!(
+            generate_puzzle(&mut rng, 5, 1).unwrap_err(),
+            PuzzleError::TooManyLarge(5)
+        );
+        assert_eq!(
+            generate_puzzle(&mut rng, 1, 4).unwrap_err(),
+            PuzzleError::TileCount(5)
+        );
+    }
+
+    #[test]
+    fn test_validate_tiles() {
+        assert_eq!(validate_tiles(&[5, 25, 2, 50, 100, 10]), Ok(()));
+        assert_eq!(validate_tiles(&[7, 7, 1, 2, 3, 4]), Ok(()));
+
+        assert_eq!(
+            validate_tiles(&[5, 25, 2, 50, 100]),
+            Err(TileError::Count {
+                expected: 6,
+                found: 5
+            })
+        );
+        assert_eq!(
+            validate_tiles(&[5, 25, 2, 50, 100, 11]),
+            Err(TileError::Unknown(11))
+        );
+        assert_eq!(
+            validate_tiles(&[5, 5, 5, 50, 100, 10]),
+            Err(TileError::TooMany { tile: 5, max: 2 })
+        );
+        assert_eq!(
+            validate_tiles(&[5, 25, 2, 50, 50, 10]),
+            Err(TileError::TooMany { tile: 50, max: 1 })
+        );
+
+        // Any number of tiles, each large one twice
+        let rules = TileRules {
+            count: None,
+            large_copies: 2,
+            ..TileRules::default()
+        };
+        assert_eq!(validate_tiles_with(&[50, 50, 3], &rules), Ok(()));
+        assert!(validate_tiles_with(&[50, 50, 50], &rules).is_err());
+    }
+
+    #[test]
+    fn test_difficulty() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        assert_eq!(difficulty(&numbers, 100), Ok(Difficulty::Trivial));
+        assert_eq!(difficulty(&numbers, 250), Ok(Difficulty::Trivial));
+        assert_eq!(difficulty(&numbers, 252), Ok(Difficulty::Easy));
+        assert_eq!(difficulty(&numbers, 997), Ok(Difficulty::Medium));
+        assert_eq!(difficulty(&numbers, 777), Ok(Difficulty::Medium));
+        assert_eq!(difficulty(&numbers, 943), Ok(Difficulty::Hard));
+        assert_eq!(difficulty(&numbers, 281), Ok(Difficulty::Hard));
+        assert_eq!(difficulty(&numbers, 831), Ok(Difficulty::Unsolvable));
+
+        let numbers = vec![1, 3, 7, 10, 25, 50];
+        assert_eq!(difficulty(&numbers, 813), Ok(Difficulty::Hard));
+
+        assert!(Difficulty::Trivial < Difficulty::Easy);
+        assert!(Difficulty::Hard < Difficulty::Unsolvable);
+        assert_eq!(Difficulty::Medium.to_string(), "Medium");
+    }
+
+    #[test]
+    fn test_solvable_targets() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let targets = solvable_targets(&numbers, 100..=999).unwrap();
+
+        let results = all_combinations(&numbers, MAX_WORKERS).unwrap();
+        let expected = results.keys().filter(|x| (100..=999).contains(*x)).count();
+        assert_eq!(targets.len(), expected);
+
+        assert!(!targets.contains_key(&831));
+        assert_eq!(targets[&281].len(), 5);
+        assert_eq!(targets.keys().next(), Some(&100));
+    }
+
+    #[test]
+    fn test_hardest_target() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        let (target, number) = hardest_target(&numbers, 100..=999).unwrap().unwrap();
+        assert_eq!(number.value, target);
+        assert_eq!(number.len(), 5);
+
+        let combinations = all_combinations(&numbers, 1).unwrap();
+        let longer = combinations
+            .iter()
+            .filter(|(value, _)| (100..=999).contains(*value))
+            .filter(|(value, x)| x.len() > 5 || (x.len() == 5 && **value < target));
+        assert_eq!(longer.count(), 0);
+
+        // Nothing reachable
+        assert!(hardest_target(&[1, 1], 10..=20).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_solve_all() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        let solutions = solve_all(&numbers, 250).unwrap();
+
+        assert!(solutions.len() > 1);
+        assert!(solutions.iter().all(|x| x.value == 250));
+        // Shortest first (10 * 25)
+        assert_eq!(solutions[0].len(), 1);
+
+        let mut keys: Vec<_> = solutions.iter().map(|x| x.canonical_key()).collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), solutions.len());
+    }
+
+    #[test]
+    fn test_cmp_by_complexity() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let mut solutions: Vec<Number> = [281, 250, 100, 997]
+            .iter()
+            .map(|x| solve(&numbers, *x, 0).unwrap().unwrap())
+            .collect();
+
+        solutions.sort_by(Number::cmp_by_complexity);
+        let values: Vec<i32> = solutions.iter().map(|x| x.value).collect();
+        assert_eq!(values, vec![100, 250, 997, 281]);
+
+        // 2 + 3 and 3 + 2 are the same calculus
+        let (two, three) = (Number::from_int(2), Number::from_int(3));
+        let a = Number::from(5, Operation::Addition, &two, &three);
+        let b = Number::from(5, Operation::Addition, &three, &two);
+        assert_eq!(a.cmp_by_complexity(&b), std::cmp::Ordering::Equal);
+        assert_eq!(a.cmp_by_complexity(&two), std::cmp::Ordering::Greater);
+    }
+
+    #[test]
+    fn test_by_value() {
+        let (two, three) = (Number::from_int(2), Number::from_int(3));
+        let sum = Number::from(5, Operation::Addition, &two, &three);
+        let difference = Number::from(1, Operation::Subtraction, &three, &two);
+        let five = Number::from_int(5);
+
+        let distinct: HashSet<ByValue> = [sum, difference, five].into_iter().map(ByValue).collect();
+        assert_eq!(distinct.len(), 2);
+        assert!(distinct.contains(&ByValue(Number::from_int(1))));
+    }
+
+    #[test]
+    fn test_to_expr() {
+        let result = solve(&[50, 25, 2], 150, 0).unwrap().unwrap();
+        let expr = result.to_expr();
+
+        let sum = Expr::Op {
+            op: Operation::Addition,
+            lhs: Box::new(Expr::Value(50)),
+            rhs: Box::new(Expr::Value(25)),
+        };
+        let expected = Expr::Op {
+            op: Operation::Multiplication,
+            lhs: Box::new(Expr::Value(2)),
+            rhs: Box::new(sum),
+        };
+        assert_eq!(expr, expected);
+        assert_eq!(expr.as_expression(), result.as_expression());
+
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        assert_eq!(result.to_expr().eval(), Some(281));
+        assert_eq!(Number::from_int(7).to_expr(), Expr::Value(7));
+    }
+
+    #[test]
+    fn test_expr_eval() {
+        let parse = |x| Expr::parse(x).unwrap();
+        assert_eq!(parse("2 * (50 + 25)").eval(), Some(150));
+
+        // Anywhere in the tree
+        assert_eq!(parse("(2 - 3) + 10").eval(), None);
+        assert_eq!(parse("10 * (7 / 2)").eval(), None);
+        assert_eq!(parse("100 + 5 / (3 - 3)").eval(), None);
+        assert_eq!(parse("2147483647 + 1 - 1").eval(), None);
+
+        // 1 + (1 + (1 + …))
+        let mut expr = Expr::Value(1);
+        for _ in 0..1000 {
+            expr = Expr::Op {
+                op: Operation::Addition,
+                lhs: Box::new(Expr::Value(1)),
+                rhs: Box::new(expr),
+            };
+        }
+        assert_eq!(expr.eval(), Some(1001));
+    }
+
+    #[test]
+    fn test_expr_simplify() {
+        let parse = |x| Expr::parse(x).unwrap();
+        let simplified = |x| parse(x).simplify().as_expression();
+
+        assert_eq!(simplified("2 + (50 + 25)"), "((2 + 25) + 50)");
+        assert_eq!(
+            parse("(25 + 2) + 50").simplify(),
+            parse("2 + (50 + 25)").simplify()
+        );
+        assert_eq!(simplified("(25 + 50) * 2"), "(2 * (25 + 50))");
+        assert_eq!(simplified("10 * (3 * 7)"), "((3 * 7) * 10)");
+
+        // Subtractions and divisions keep their operands in place
+        assert_eq!(simplified("(50 - 25) - 2"), "((50 - 25) - 2)");
+        assert_eq!(simplified("100 / (25 + 75) - 1"), "((100 / (25 + 75)) - 1)");
+        assert_ne!(parse("50 - 25").simplify(), parse("25 - 50").simplify());
+
+        let expr = parse("(100 - 4) * (3 + 7) + 5 * (2 + 1)");
+        assert_eq!(expr.simplify().eval(), expr.eval());
+        assert_eq!(expr.simplify().canonical_key(), expr.canonical_key());
+    }
+
+    #[test]
+    fn test_as_infix() {
+        let infix = |x| Expr::parse(x).unwrap().as_infix();
+
+        assert_eq!(infix("(50 + 25) + 2"), "50 + 25 + 2");
+        assert_eq!(infix("50 + (25 + 2)"), "50 + 25 + 2");
+        assert_eq!(infix("50 + (25 - 2)"), "50 + (25 - 2)");
+
+        // Subtractions and divisions
+        assert_eq!(infix("(50 - 25) - 2"), "50 - 25 - 2");
+        assert_eq!(infix("50 - (25 - 2)"), "50 - (25 - 2)");
+        assert_eq!(infix("50 - (25 + 2)"), "50 - (25 + 2)");
+        assert_eq!(infix("(100 / 10) / 2"), "100 / 10 / 2");
+        assert_eq!(infix("100 / (10 / 2)"), "100 / (10 / 2)");
+        assert_eq!(infix("100 / (10 * 2)"), "100 / (10 * 2)");
+
+        // Mixed precedence
+        assert_eq!(infix("2 + (3 * 4)"), "2 + 3 * 4");
+        assert_eq!(infix("(2 + 3) * 4"), "(2 + 3) * 4");
+        assert_eq!(infix("(10 - 4) / (1 + 2)"), "(10 - 4) / (1 + 2)");
+        assert_eq!(infix("100 - (10 / 2)"), "100 - 10 / 2");
+
+        assert_eq!(infix("3 * (7 % 4)"), "3 * (7 % 4)");
+        assert_eq!(infix("(3 * 7) % 4"), "3 * 7 % 4");
+        assert_eq!(infix("12 / (2 * 3)"), "12 / (2 * 3)");
+        assert_eq!(infix("2 * (12 / 3)"), "2 * (12 / 3)");
+
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        let expr = Expr::parse(&result.as_infix()).unwrap();
+        assert_eq!(expr, result.to_expr());
+
+        // Read back, the infix notation has the same value
+        let n = Number::from_int;
+        let modulo = Number::from(3, Operation::Modulo, &n(7), &n(4));
+        let product = Number::from(9, Operation::Multiplication, &n(3), &modulo);
+        let sum = Number::from(12, Operation::Addition, &n(3), &product);
+        let difference = Number::from(3, Operation::Subtraction, &n(12), &product);
+        for number in [modulo, product, sum, difference] {
+            let expr = Expr::parse(&number.as_infix()).unwrap();
+            assert_eq!(expr.eval(), Some(number.value), "{}", number.as_infix());
+        }
+        let options = SolveOptions::new()
+            .operations(OperationSet::ALL.with(Operation::Modulo))
+            .build();
+        let results = all_combinations_with(&[3, 7, 4, 25], &options).unwrap();
+        for number in results.values() {
+            let expr = Expr::parse(&number.as_infix()).unwrap();
+            assert_eq!(expr.eval(), Some(number.value), "{}", number.as_infix());
+        }
+    }
+
+    #[test]
+    fn test_canonical_key() {
+        let n = Number::from_int;
+        let (a, b, c) = (n(50), n(25), n(2));
+
+        // (50 + 25) + 2 and 2 + (25 + 50)
+        let ab = Number::from(75, Operation::Addition, &a, &b);
+        let left = Number::from(77, Operation::Addition, &ab, &c);
+        let ba = Number::from(75, Operation::Addition, &b, &a);
+        let right = Number::from(77, Operation::Addition, &c, &ba);
+
+        assert_eq!(left.canonical_key(), "(2 + 25 + 50)");
+        assert_eq!(left.canonical_key(), right.canonical_key());
+
+        // Not for subtractions
+        let ab = Number::from(25, Operation::Subtraction, &a, &b);
+        let left = Number::from(23, Operation::Subtraction, &ab, &c);
+        let ac = Number::from(48, Operation::Subtraction, &a, &c);
+        let right = Number::from(23, Operation::Subtraction, &ac, &b);
+
+        assert_eq!(left.canonical_key(), "((50 - 25) - 2)");
+        assert_ne!(left.canonical_key(), right.canonical_key());
+    }
+
+    #[test]
+    fn test_operation_apply() {
+        use Operation::*;
+
+        let cases = [
+            (Addition, 2, 3, Some(5)),
+            (Addition, i32::MAX, 1, None),
+            (Multiplication, 7, 1, Some(7)),
+            (Multiplication, i32::MAX, 2, None),
+            (Subtraction, 5, 3, Some(2)),
+            (Subtraction, 3, 3, None),
+            (Subtraction, 3, 5, None),
+            (Division, 6, 3, Some(2)),
+            (Division, 7, 7, Some(1)),
+            (Division, 7, 2, None),
+            (Division, 7, 0, None),
+            (Division, 2, 6, None),
+        ];
+        for (op, a, b, expected) in cases {
+            assert_eq!(op.apply(a, b), expected, "{a} {op} {b}");
+        }
+
+        assert_eq!(
+            Multiplication.apply(100_000i64, 100_000),
+            Some(10_000_000_000)
+        );
+    }
+
+    #[test]
+    fn test_moperation_evaluate() {
+        assert_eq!(MOperation(Operation::Division, 8, 2).evaluate(), Some(4));
+        assert_eq!(MOperation(Operation::Subtraction, 3, 5).evaluate(), None);
+
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        for step in result.steps() {
+            let operation = MOperation(step.op, step.lhs, step.rhs);
+            assert_eq!(operation.evaluate(), Some(step.result));
+        }
+    }
+
+    #[test]
+    fn test_operation_from_str() {
+        for op in [
+            Operation::Addition,
+            Operation::Multiplication,
+            Operation::Subtraction,
+            Operation::Division,
+            Operation::Power,
+            Operation::Modulo,
+            Operation::Concatenation,
+        ] {
+            assert_eq!(op.to_string().parse::<Operation>(), Ok(op));
+        }
+        assert_eq!(" * ".parse(), Ok(Operation::Multiplication));
+        assert_eq!(Operation::try_from('/'), Ok(Operation::Division));
+
+        assert_eq!(
+            "x".parse::<Operation>(),
+            Err(ParseError::UnknownOperation("x".to_string()))
+        );
+        assert!("++".parse::<Operation>().is_err());
+        assert!("".parse::<Operation>().is_err());
+        assert_eq!(
+            Operation::try_from('&').unwrap_err().to_string(),
+            "'&' is not an operation (expected one of + - * / ^ % |)"
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("2 * (50 + 25)"), Ok(150));
+        assert_eq!(evaluate("2 + 50 * 3 - 100 / 4"), Ok(127));
+        assert_eq!(evaluate("100 - 50 - 25"), Ok(25));
+        assert_eq!(evaluate("  ((7)) "), Ok(7));
+
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        assert_eq!(evaluate(&result.as_expression()), Ok(281));
+
+        assert_eq!(
+            evaluate("7 / 2"),
+            Err(ParseError::Forbidden(
+                MOperation(Operation::Division, 7, 2),
+                Violation::NotInteger
+            ))
+        );
+        assert_eq!(
+            evaluate("7 / (2 - 2)"),
+            Err(ParseError::Forbidden(
+                MOperation(Operation::Subtraction, 2, 2),
+                Violation::NotPositive
+            ))
+        );
+        assert_eq!(
+            evaluate("7 / 0").unwrap_err().to_string(),
+            "7 / 0 is not allowed: division by zero"
+        );
+        assert_eq!(evaluate("(2 + 3"), Err(ParseError::UnexpectedEnd));
+        assert_eq!(evaluate("2 + x"), Err(ParseError::UnexpectedChar('x', 4)));
+        assert_eq!(evaluate("2 3"), Err(ParseError::UnexpectedChar('3', 2)));
+        assert!(matches!(
+            evaluate("99999999999"),
+            Err(ParseError::InvalidNumber(_))
+        ));
+
+        // Deep nesting is an error, not a stack overflow
+        let nested = |n| format!("{}1{}", "(".repeat(n), ")".repeat(n));
+        assert_eq!(evaluate(&nested(64)), Ok(1));
+        assert_eq!(evaluate(&nested(65)), Err(ParseError::TooDeep(64)));
+        assert!(matches!(
+            evaluate(&nested(300_000)),
+            Err(ParseError::TooDeep(_))
+        ));
+        assert!(matches!(
+            evaluate(&"1 ^ ".repeat(300_000)),
+            Err(ParseError::TooDeep(_))
+        ));
+        // As are long chains of operations, that make trees as deep
+        let chain = |op, n| format!("1{}", format!(" {op} 1").repeat(n));
+        assert_eq!(evaluate(&chain('+', 1000)), Ok(1001));
+        assert_eq!(evaluate(&chain('*', 1000)), Ok(1));
+        for op in ['+', '*'] {
+            assert!(matches!(
+                evaluate(&chain(op, 20_000)),
+                Err(ParseError::TooDeep(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_verify() {
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        assert_eq!(result.verify(), Ok(()));
+
+        let (two, three, seven) = (
+            Number::from_int(2),
+            Number::from_int(3),
+            Number::from_int(7),
+        );
+
+        // Wrong value
+        let tampered = Number::from(6, Operation::Addition, &two, &three);
+        assert_eq!(
+            tampered.verify(),
+            Err(VerifyError::Mismatch {
+                expected: 5,
+                found: 6
+            })
+        );
+
+        // Forbidden operation, hidden in an operand
+        let half = Number::from(3, Operation::Division, &seven, &two);
+        let tampered = Number::from(6, Operation::Multiplication, &half, &two);
+        assert_eq!(
+            tampered.verify(),
+            Err(VerifyError::Forbidden(
+                MOperation(Operation::Division, 7, 2),
+                Violation::NotInteger
+            ))
+        );
+
+        // Forbidden with the default rules only
+        let zero = Number::from(0, Operation::Subtraction, &two, &two);
+        assert!(zero.verify().is_err());
+        assert_eq!(
+            zero.verify_with(&SolveOptions::new().zero(true).build()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_to_latex() {
+        let result = solve(&[50, 25, 2], 150, 0).unwrap().unwrap();
+        assert_eq!(result.to_latex(), "2 \\times (50 + 25)");
+
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        assert_eq!(
+            result.to_latex(),
+            "5 + (2 + 10) \\times (25 - \\frac{100}{50})"
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let result = solve(&[50, 25, 2], 150, 0).unwrap().unwrap();
+
+        let expected = [
+            "digraph {",
+            "    n0 [label=\"* = 150\"];",
+            "    n0 -> n1;",
+            "    n0 -> n2;",
+            "    n1 [label=\"2\", shape=box];",
+            "    n2 [label=\"+ = 75\"];",
+            "    n2 -> n3;",
+            "    n2 -> n4;",
+            "    n3 [label=\"50\", shape=box];",
+            "    n4 [label=\"25\", shape=box];",
+            "}",
+        ];
+        assert_eq!(result.to_dot(), expected.join("\n"));
+        assert_eq!(
+            Number::from_int(7).to_dot(),
+            "digraph {\n    n0 [label=\"7\", shape=box];\n}"
+        );
+    }
+
+    #[test]
+    fn test_from_preorder() {
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+
+        let rebuilt = Number::from_preorder(result.value, &result.operations()).unwrap();
+        assert_eq!(rebuilt.as_expression(), result.as_expression());
+        assert_eq!(rebuilt.verify(), Ok(()));
+
+        let mut operations = result.operations();
+        operations[1].1 += 1;
+        assert!(Number::from_preorder(result.value, &operations).is_none());
+        assert!(Number::from_preorder(result.value + 1, &result.operations()).is_none());
+
+        // 75 + (50 + 25): the same as (50 + 25) + 75
+        let operations = [
+            MOperation(Operation::Addition, 75, 75),
+            MOperation(Operation::Addition, 50, 25),
+        ];
+        let rebuilt = Number::from_preorder(150, &operations).unwrap();
+        assert_eq!(rebuilt.as_expression(), "((50 + 25) + 75)");
+
+        let division = [MOperation(Operation::Division, 1, 0)];
+        assert!(Number::from_preorder(0, &division).is_none());
+    }
+
+    #[test]
+    fn test_from_operations() {
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+
+        let rebuilt = Number::from_operations(281, &result.operations()).unwrap();
+        assert_eq!(rebuilt.as_expression(), result.as_expression());
+        assert_eq!(Number::from_operations(7, &[]).unwrap().value, 7);
+
+        // The steps lead to 150, not 151
+        let operations = [
+            MOperation(Operation::Multiplication, 2, 75),
+            MOperation(Operation::Addition, 50, 25),
+        ];
+        assert_eq!(
+            Number::from_operations(151, &operations).unwrap_err(),
+            VerifyError::Mismatch {
+                expected: 150,
+                found: 151
+            }
+        );
+
+        // 2 * 3 is not an operand of 2 * 75
+        let mut operations = operations.to_vec();
+        operations[1] = MOperation(Operation::Multiplication, 2, 3);
+        assert_eq!(
+            Number::from_operations(150, &operations).unwrap_err(),
+            VerifyError::Unused(MOperation(Operation::Multiplication, 2, 3))
+        );
+
+        let forbidden = [MOperation(Operation::Subtraction, 2, 3)];
+        assert_eq!(
+            Number::from_operations(-1, &forbidden).unwrap_err(),
+            VerifyError::Forbidden(forbidden[0], Violation::NotPositive)
+        );
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi() {
+        use std::ffi::CStr;
+
+        let numbers = [50, 25, 2];
+        unsafe {
+            let solution = deschiffres_solve(numbers.as_ptr(), numbers.len(), 150, 0);
+            assert!(!solution.is_null());
+            assert_eq!((*solution).value, 150);
+            let expression = CStr::from_ptr((*solution).expression);
+            assert_eq!(expression.to_str(), Ok("(2 * (50 + 25))"));
+            deschiffres_free(solution);
+
+            assert!(deschiffres_solve(numbers.as_ptr(), numbers.len(), 999, 0).is_null());
+            assert!(deschiffres_solve(std::ptr::null(), 3, 150, 0).is_null());
+            deschiffres_free(std::ptr::null_mut());
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_solve_js_typed() {
+        let numbers = [50, 25, 2];
+        let solution = solve_js_typed(&numbers, 150, 0).unwrap().unwrap();
+        assert_eq!(solution.value(), 150);
+        assert_eq!(solution.steps(), ["50 + 25 = 75", "2 * 75 = 150"]);
+        assert_eq!(solution.expression(), "(2 * (50 + 25))");
+        assert!(solution.is_exact());
+
+        let solution = solve_js_typed(&numbers, 999, 1000).unwrap().unwrap();
+        assert!(!solution.is_exact());
+        assert!(solve_js_typed(&numbers, 999, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_numbers_used() {
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        assert_eq!(result.numbers_used(), 6);
+
+        let result = solve(&[5, 25, 2, 50, 100, 10], 250, 0).unwrap().unwrap();
+        assert_eq!(result.numbers_used(), 2);
+
+        let result = solve(&[5, 25, 2, 50, 100, 10], 100, 0).unwrap().unwrap();
+        assert_eq!(result.numbers_used(), 1);
+    }
+
+    #[test]
+    fn test_tiles() {
+        let operations = [
+            MOperation(Operation::Multiplication, 75, 2),
+            MOperation(Operation::Addition, 50, 25),
+        ];
+        let result = Number::from_operations(150, &operations).unwrap();
+        assert_eq!(result.tiles(), vec![50, 25, 2]);
+        assert_eq!(Number::from_int(7).tiles(), vec![7]);
+
+        // Both 2 are used
+        let result = solve(&[2, 2, 3], 7, 0).unwrap().unwrap();
+        assert_eq!(
+            result.tiles().into_iter().sorted().collect_vec(),
+            vec![2, 2, 3]
+        );
+        assert_eq!(result.tiles().len(), result.numbers_used());
+    }
+
+    #[test]
+    fn test_uses_valid_tiles() {
+        let available = [2, 2, 3];
+
+        // 2 * 2 + 3
+        let result = solve(&available, 7, 0).unwrap().unwrap();
+        assert!(result.uses_valid_tiles(&available));
+        assert!(!result.uses_valid_tiles(&[2, 3]));
+
+        // 2 * (2 * 2), a 2 more than given
+        let operations = [
+            MOperation(Operation::Multiplication, 2, 4),
+            MOperation(Operation::Multiplication, 2, 2),
+        ];
+        let result = Number::from_operations(8, &operations).unwrap();
+        assert!(!result.uses_valid_tiles(&available));
+        assert!(result.uses_valid_tiles(&[2, 2, 2, 5]));
+
+        for result in all_combinations(&available, 0).unwrap().values() {
+            assert!(result.uses_valid_tiles(&available));
+        }
+    }
+
+    #[test]
+    fn test_depth() {
+        assert_eq!(Number::from_int(7).depth(), 0);
+
+        let result = solve(&[50, 25, 2], 150, 0).unwrap().unwrap();
+        assert_eq!(result.depth(), 2);
+
+        // 5 + (2 + 10) * (25 - 100 / 50): 5 operations, at most 4 on the way to 100
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        assert_eq!(result.depth(), 4);
+
+        // (2 + 3) * (4 + 5) and ((2 + 3) + 4) * 5 both have 3 operations
+        let balanced = Number::from_operations(
+            45,
+            &[
+                MOperation(Operation::Multiplication, 5, 9),
+                MOperation(Operation::Addition, 2, 3),
+                MOperation(Operation::Addition, 4, 5),
+            ],
+        )
+        .unwrap();
+        let chain = Number::from_operations(
+            45,
+            &[
+                MOperation(Operation::Multiplication, 9, 5),
+                MOperation(Operation::Addition, 5, 4),
+                MOperation(Operation::Addition, 2, 3),
+            ],
+        )
+        .unwrap();
+        assert_eq!((balanced.len(), balanced.depth()), (3, 2));
+        assert_eq!((chain.len(), chain.depth()), (3, 3));
+    }
+
+    #[test]
+    fn test_steps() {
+        let result = solve(&[50, 25, 2], 150, 0).unwrap().unwrap();
+
+        let steps = result.steps();
+        assert_eq!(
+            steps,
+            vec![
+                Step {
+                    lhs: 50,
+                    op: Operation::Addition,
+                    rhs: 25,
+                    result: 75
+                },
+                Step {
+                    lhs: 2,
+                    op: Operation::Multiplication,
+                    rhs: 75,
+                    result: 150
+                },
+            ]
+        );
+        assert_eq!(result.as_text(), "50 + 25 = 75\n2 * 75 = 150");
+        assert_eq!(format!("{result}"), result.as_text());
+        assert_eq!(steps[0].to_string(), "50 + 25 = 75");
+
+        assert_eq!(result.max_intermediate(), 150);
+        assert_eq!(result.min_intermediate(), 2);
+        assert_eq!(
+            result.operation_counts(),
+            BTreeMap::from([(Operation::Addition, 1), (Operation::Multiplication, 1)])
+        );
+        assert_eq!(
+            result.to_json(),
+            r#"{"value":150,"operations":[["*",2,75],["+",50,25]],"max_intermediate":150,"min_intermediate":2,"operation_counts":{"+":1,"*":1}}"#
+        );
+        let result = Number::from_int(7);
+        assert_eq!(
+            (result.min_intermediate(), result.max_intermediate()),
+            (7, 7)
+        );
+        assert!(result.operation_counts().is_empty());
+    }
+
+    #[test]
+    fn test_as_expression() {
+        let result = solve(&[50, 25, 2], 150, 0).unwrap().unwrap();
+        assert_eq!(result.as_expression(), "(2 * (50 + 25))");
+
+        let result = solve(&[50, 25, 2], 50, 0).unwrap().unwrap();
+        assert_eq!(result.as_expression(), "50");
+
+        // 50 - (25 - 2)
+        let number = Number::from(
+            27,
+            Operation::Subtraction,
+            &Number::from_int(50),
+            &Number::from(
+                23,
+                Operation::Subtraction,
+                &Number::from_int(25),
+                &Number::from_int(2),
+            ),
+        );
+        assert_eq!(number.as_expression(), "(50 - (25 - 2))");
+
+        // Same values on both sides: the left 6 is a tile, the right one is 2 * 3
+        let number = Number::from(
+            12,
+            Operation::Addition,
+            &Number::from_int(6),
+            &Number::from(
+                6,
+                Operation::Multiplication,
+                &Number::from_int(2),
+                &Number::from_int(3),
+            ),
+        );
+        assert_eq!(number.as_expression(), "(6 + (2 * 3))");
+    }
+
+    #[test]
+    fn test_as_rpn() {
+        let result = solve(&[5, 25, 2, 50, 100, 10], 281, 0).unwrap().unwrap();
+        let rpn = result.as_rpn();
+
+        let mut stack = vec![];
+        for token in rpn.split(' ') {
+            if let Ok(n) = token.parse::<i32>() {
+                stack.push(n);
+                continue;
+            }
+            let b = stack.pop().unwrap();
+            let a = stack.pop().unwrap();
+            stack.push(match token {
+                "+" => a + b,
+                "-" => a - b,
+                "*" => a * b,
+                "/" => a / b,
+                _ => panic!("unexpected token {token}"),
+            });
+        }
+
+        assert_eq!(stack, vec![result.value]);
+        assert_eq!(
+            solve(&[50, 25, 2], 150, 0).unwrap().unwrap().as_rpn(),
+            "2 50 25 + *"
+        );
+    }
+
+    #[test]
+    fn test_combinations_operations() {
+        let numbers = vec![5, 25, 2, 50, 10];
+        let operations = OperationSet::ALL.without(Operation::Division);
+        let options = SolveOptions::new().operations(operations).build();
+
+        let combinations = all_combinations_with(&numbers, &options).unwrap();
+
+        assert!(!combinations.is_empty());
+        for number in combinations.values() {
+            assert!(!number
+                .operations()
+                .iter()
+                .any(|x| matches!(x.0, Operation::Division)));
+        }
+
+        let operations = [Operation::Addition, Operation::Multiplication];
+        let options = SolveOptions::new()
+            .operations(operations.into_iter().collect())
+            .build();
+        assert!(solve_with(&[2, 5], 3, &options).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_solve_power() {
+        // At most 10 * 3 * 2 = 60 without powers
+        assert!(solve(&[2, 3, 10], 1000, 0).unwrap().is_none());
+
+        let operations = OperationSet::ALL.with(Operation::Power);
+        let options = SolveOptions::new().operations(operations).build();
+        let result = solve_with(&[2, 3, 10], 1000, &options).unwrap().unwrap();
+        assert_eq!(result.as_infix(), "10 ^ 3");
+        assert_eq!(result.verify(), Ok(()));
+
+        assert_eq!(Operation::Power.apply(2, 10), Some(1024));
+        assert_eq!(Operation::Power.apply(2, 11), None);
+        assert_eq!(Operation::Power.apply(100, 5), None);
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), Ok(512));
+        assert_eq!(evaluate("(2 ^ 3) ^ 2 * 2"), Ok(128));
+    }
+
+    #[test]
+    fn test_solve_modulo() {
+        assert!(solve(&[7, 100], 2, 0).unwrap().is_none());
+
+        let operations = OperationSet::ALL.with(Operation::Modulo);
+        let options = SolveOptions::new().operations(operations).build();
+        let result = solve_with(&[7, 100], 2, &options).unwrap().unwrap();
+        assert_eq!(result.as_text(), "100 % 7 = 2");
+        assert_eq!(result.as_expression(), "(100 % 7)");
+
+        assert_eq!(Operation::Modulo.apply(100, 7), Some(2));
+        assert_eq!(Operation::Modulo.apply(7, 100), Some(7));
+        assert_eq!(Operation::Modulo.apply(7, 0), None);
+        assert_eq!(Operation::Modulo.apply(14, 7), None);
+        assert_eq!(
+            evaluate("7 % 0").unwrap_err().to_string(),
+            "7 % 0 is not allowed: division by zero"
+        );
+        assert_eq!(evaluate("100 % (2 * 7) * 3"), Ok(6));
+    }
+
+    #[test]
+    fn test_solve_concatenation() {
+        assert!(solve(&[2, 5], 25, 0).unwrap().is_none());
+
+        let options = SolveOptions::new().concatenation(true).build();
+        let result = solve_with(&[2, 5], 25, &options).unwrap().unwrap();
+        assert_eq!(result.as_text(), "2 | 5 = 25");
+        assert_eq!(result.as_infix(), "25");
+        assert_eq!(result.as_expression(), "25");
+        assert!(solve_with(&[2, 5], 52, &options).unwrap().is_some());
+
+        // Results are not concatenated: 7 | 1 would need 2 + 5
+        let result = solve_with(&[2, 5, 1], 71, &options).unwrap();
+        assert!(result.is_none());
+        let result = solve_with(&[2, 5, 4], 100, &options).unwrap().unwrap();
+        assert_eq!(result.as_infix(), "4 * 25");
+
+        assert_eq!(Operation::Concatenation.apply(12, 0), Some(120));
+        assert_eq!(Operation::Concatenation.apply(i32::MAX, 1), None);
+    }
+
+    #[test]
+    fn test_solve_approximation() {
+        // 2 + 5 = 7 (-1), 2 * 5 = 10 (+2)
+        let result = solve(&[2, 5], 8, 3).unwrap().unwrap();
+        assert_eq!(result.value, 7);
+
+        assert!(solve(&[2, 5], 8, 0).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_solve_with_workers() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        for workers in [0, 4] {
+            let result = solve_with_workers(&numbers, 281, 0, workers)
+                .unwrap()
+                .unwrap();
+            assert_eq!(result.value, 281);
+        }
+    }
+
+    #[test]
+    fn test_solve_with_options() {
+        let options = SolveOptions::new().approximation(3).max_workers(4).build();
+
+        let result = solve_with(&[2, 5], 8, &options).unwrap().unwrap();
+        assert_eq!(result.value, 7);
+    }
+
+    #[test]
+    fn test_solve_single_number() {
+        let result = solve(&[50, 25, 100], 100, 0).unwrap().unwrap();
+
+        assert_eq!(result.value, 100);
+        assert!(result.operations().is_empty());
+    }
+
+    #[test]
+    fn test_solver_cache() {
+        let solver = Solver::with_capacity(2);
+        assert!(solver.is_empty());
+
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let result = solver.solve(&numbers, 281, 0).unwrap().unwrap();
+        assert_eq!(result.value, 281);
+        assert_eq!(
+            result.len(),
+            solve(&numbers, 281, 0).unwrap().unwrap().len()
+        );
+
+        // The same tiles in another order are a hit
+        let result = solver
+            .solve(&[100, 50, 25, 10, 5, 2], 831, 5)
+            .unwrap()
+            .unwrap();
+        let expected = solve(&numbers, 831, 5).unwrap().unwrap();
+        assert_eq!(
+            (result.value, result.len()),
+            (expected.value, expected.len())
+        );
+        assert_eq!(solver.len(), 1);
+
+        solver.solve(&[1, 3, 7, 10, 25, 50], 765, 0).unwrap();
+        solver.solve(&numbers, 250, 0).unwrap();
+        // The least recently used set is [1, 3, 7, 10, 25, 50]
+        solver.solve(&[3, 3, 3, 7, 7, 100], 700, 0).unwrap();
+        assert_eq!(solver.len(), 2);
+
+        let solver = Arc::new(Solver::with_capacity(0));
+        let shared = solver.clone();
+        let found = std::thread::spawn(move || shared.solve(&[50, 25, 2], 150, 0))
+            .join()
+            .unwrap();
+        assert_eq!(found.unwrap().unwrap().value, 150);
+        assert!(solver.is_empty());
+    }
+
+    #[test]
+    fn test_incremental_solver() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let mut solver = IncrementalSolver::new();
+
+        for i in 0..numbers.len() {
+            solver.add_number(numbers[i]).unwrap();
+
+            let expected = all_combinations(&numbers[..=i], MAX_WORKERS).unwrap();
+            let results = solver.results();
+            assert_eq!(results.len(), expected.len());
+            for (value, number) in results {
+                assert_eq!(number.len(), expected[value].len());
+                assert!(number.verify().is_ok());
+            }
+        }
+
+        assert_eq!(solver.numbers(), numbers);
+        assert_eq!(solver.solve(281, 0).unwrap().len(), 5);
+
+        // The same Numbers every time (each HashMap iterates in its own order)
+        let mut other = IncrementalSolver::new();
+        for number in numbers {
+            other.add_number(number).unwrap();
+        }
+        for (value, number) in solver.results() {
+            assert_eq!(other.results()[value].operations(), number.operations());
+        }
+    }
+
+    #[test]
+    fn test_solve_fewest_numbers() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+
+        // 25 + (50 + 100), while some solutions use 5 numbers
+        let result = solve(&numbers, 175, 0).unwrap().unwrap();
+        assert_eq!(result.numbers_used(), 3);
+        let solutions = solve_all(&numbers, 175).unwrap();
+        assert!(solutions.iter().any(|x| x.numbers_used() == 5));
+
+        assert_eq!(solve(&numbers, 100, 0).unwrap().unwrap().numbers_used(), 1);
+        assert!(solve(&numbers, 831, 0).unwrap().is_none());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_solve_async() {
+        use std::future::Future;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        // The smallest executor: park until woken up
+        struct Unpark(std::thread::Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let mut future = std::pin::pin!(future);
+            let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+            let mut cx = Context::from_waker(&waker);
+            loop {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                    return output;
+                }
+                std::thread::park();
+            }
+        }
+
+        let result = block_on(solve_async(&[5, 25, 2, 50, 100, 10], 281, 0));
+        assert_eq!(result.unwrap().unwrap().value, 281);
+        assert_eq!(
+            block_on(solve_async(&[], 281, 0)).unwrap_err(),
+            SolveError::EmptyInput
+        );
+
+        // Dropped right away: the search stops
+        drop(solve_async(&[5, 25, 2, 50, 100, 10], 281, 0));
+    }
+
+    #[test]
+    fn test_solve_many() {
+        let numbers = [5, 25, 2, 50, 100, 10];
+        let targets = [281, 831, 250, 281];
+
+        let solved = solve_many(&numbers, &targets, 5).unwrap();
+        assert_eq!(solved.len(), 3);
+        for target in targets {
+            let expected = solve(&numbers, target, 5).unwrap().unwrap();
+            let found = solved[&target].as_ref().unwrap();
+            assert_eq!((found.value, found.len()), (expected.value, expected.len()));
+        }
+
+        assert!(solve_many(&numbers, &[831], 0).unwrap()[&831].is_none());
+    }
+
+    #[test]
+    fn test_solve_closest() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+
+        let (exact, distance) = solve_closest(&numbers, 281).unwrap().unwrap();
+        assert_eq!(exact.value, 281);
+        assert_eq!(distance, 0);
+
+        // 1 + 1 = 2, 1 * 1 = 1
+        let (closest, distance) = solve_closest(&[1, 1], 101).unwrap().unwrap();
+        assert_eq!(closest.value, 2);
+        assert_eq!(distance, -99);
+
+        assert!(solve(&[1, 1], i32::MAX, 3).unwrap().is_none());
+        assert!(solve(&[1, 1], i32::MIN, 3).unwrap().is_none());
+        assert!(solve(&[1, 1], i32::MIN, i32::MIN).unwrap().is_none());
+        // Any value is close enough (without a lookup for each of them)
+        let result = solve(&[1, 1], -2_000_000_000, i32::MAX).unwrap().unwrap();
+        assert_eq!(result.value, 1);
+
+        let (closest, distance) = solve_closest(&[1, 1], i32::MIN).unwrap().unwrap();
+        assert_eq!(closest.value, 1);
+        assert_eq!(distance, 1 - i64::from(i32::MIN));
+    }
+
+    #[test]
+    fn test_too_many_tiles() {
+        let numbers = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 25, 50];
+        assert_eq!(
+            solve(&numbers, 999, 0).unwrap_err(),
+            SolveError::TooManyTiles { count: 12, max: 8 }
+        );
+        assert!(count_reachable(&numbers).is_err());
+
+        let options = SolveOptions::new().max_tiles(2).build();
+        assert!(solve_with(&[50, 25, 2], 150, &options).is_err());
+        let options = SolveOptions::new().max_tiles(3).build();
+        assert!(solve_with(&[50, 25, 2], 150, &options).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert_eq!(solve(&[], 100, 0).unwrap_err(), SolveError::EmptyInput);
+        assert!(all_combinations::<i32>(&[], 0).is_err());
+    }
+
+    // Combine with exactly `nworkers`, even on machines with few cores
+    fn combinations_with_workers(numbers: &[i32], nworkers: usize) -> ResultSet {
+        let mut results: ResultSet = HashMap::new();
+        search_levels(
+            numbers,
+            nworkers,
+            &SolveOptions::default(),
+            |value| {
+                keep_shortest(&mut results, value);
+            },
+            |_| {},
+        )
+        .unwrap();
+        results
+    }
+
+    #[test]
+    fn test_combinations_bounded() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+        let single = combinations_with_workers(&numbers, 1);
+
+        for capacity in [0, 1, 16] {
+            let options = SolveOptions::new().channel_capacity(capacity).build();
+            let mut results: ResultSet = HashMap::new();
+            search_levels(
+                &numbers,
+                4,
+                &options,
+                |value| {
+                    keep_shortest(&mut results, value);
+                },
+                |_| {},
+            )
+            .unwrap();
+
+            assert_eq!(results.len(), single.len());
+        }
+    }
+
+    #[test]
+    fn test_combinations_workers() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+        let single = combinations_with_workers(&numbers, 1);
+
+        for nworkers in [2, 3, 4, 8] {
+            for _ in 0..3 {
+                let multi = combinations_with_workers(&numbers, nworkers);
+                assert_eq!(multi.len(), single.len());
+                for (value, number) in &single {
+                    assert_eq!(multi[value].operations(), number.operations());
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_combinations_rayon() {
+        let numbers = vec![5, 25, 2, 50, 100, 10];
+        let single = all_combinations(&numbers, 0).unwrap();
+
+        let parallel = all_combinations_rayon(&numbers).unwrap();
+        assert_eq!(parallel.len(), single.len());
+        for (value, number) in &single {
+            assert_eq!(parallel[value].operations(), number.operations());
+        }
     }
 
     // WARNING: this test is relatively long and CPU intensive
@@ -412,7 +3351,7 @@ mod test {
     #[test]
     fn test_combinations_large() {
         let numbers = vec![5, 25, 2, 50, 10, 8, 4];
-        let combinations = all_combinations(&numbers, 5);
+        let combinations = combinations_with_workers(&numbers, 4);
 
         assert_eq!(combinations.len(), 51834);
     }
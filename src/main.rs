@@ -2,59 +2,145 @@ use std::{process::exit, time::Instant};
 
 use deschiffres::solve;
 
-fn parse_args() -> (Vec<i32>, i32) {
-    let args = std::env::args().skip(1);
-    let mut numbers: Vec<i32> = vec![];
-
-    let mut find_me = -1;
-    for argument in args {
-        let number = match argument.parse() {
-            Ok(n) => n,
-            Err(_) => continue,
-        };
-        if number > 100 {
-            find_me = number;
-            continue;
-        }
+use cli::{parse_value, Arguments};
 
-        if number < 1 {
-            continue;
-        }
+mod cli;
 
-        numbers.push(number);
-    }
+const USAGE: &str = "Usage: deschiffres [OPTIONS] --target <TARGET> <NUMBERS>...
+
+Find how to reach TARGET by combining NUMBERS with + - * /
+
+Arguments:
+  <NUMBERS>...  The numbers to combine (at least 2)
+
+Options:
+  -t, --target <TARGET>                The number to find
+  -a, --approximation <APPROXIMATION>  Accept a result up to this far from the target [default: 0]
+      --json                           Only print the result as JSON (null without a match,
+                                       {\"error\": ...} on error)
+  -h, --help                           Print help
+
+Examples:
+  deschiffres 5 25 2 50 100 10 --target 281
+  deschiffres 5 25 2 50 100 10 --target 831 --approximation 5";
 
-    if find_me == -1 {
-        eprintln!("Nothing to find (no number greater than 100)");
-        exit(1);
+struct Args {
+    numbers: Vec<i32>,
+    target: i32,
+    approximation: i32,
+    json: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Arguments::from_env();
+
+    let mut numbers = vec![];
+    let mut target = None;
+    let mut approximation = 0;
+    let mut json = false;
+
+    while let Some((option, value)) = args.next() {
+        match option.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            "-t" | "--target" => target = Some(args.value(&option, value)?),
+            "-a" | "--approximation" => approximation = args.value(&option, value)?,
+            "--json" => json = true,
+            _ if option.starts_with('-') => {
+                return Err(format!("unexpected argument '{option}'"));
+            }
+            _ => numbers.push(parse_value("<NUMBERS>", Some(option))?),
+        }
     }
 
+    let target = target.ok_or("the target is required (--target <TARGET>)")?;
     if numbers.len() < 2 {
-        eprintln!("There should be at least 2 numbers, don't you think?");
-        exit(1);
+        return Err("There should be at least 2 numbers, don't you think?".to_string());
     }
 
-    (numbers, find_me)
+    Ok(Args {
+        numbers,
+        target,
+        approximation,
+        json,
+    })
+}
+
+// A JSON string, with the quotes
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// With --json, errors are printed as {"error": "..."} where the result would be
+// (without the prefix and hint of the plain text)
+fn fail(json: bool, prefix: &str, message: &str, hint: &str) -> ! {
+    if json {
+        println!("{{\"error\":{}}}", json_string(message));
+    } else {
+        eprintln!("{prefix}{message}{hint}");
+    }
+    exit(1);
 }
 
 fn main() {
-    let (spec, to_find) = parse_args();
+    let Args {
+        numbers: spec,
+        target: to_find,
+        approximation,
+        json,
+    } = match parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            let json = std::env::args().skip(1).any(|x| x == "--json");
+            fail(
+                json,
+                "error: ",
+                &err,
+                "\n\nFor more information, try '--help'",
+            );
+        }
+    };
 
-    let approximation = 0; // Possibly try to find an approximate match up to n (int)
-    println!("Problem: find {to_find} with {spec:?}");
+    if !json {
+        println!("Problem: find {to_find} with {spec:?}");
+    }
 
     let start = Instant::now();
-    let result = solve(&spec, to_find, approximation);
+    let result = match solve(&spec, to_find, approximation) {
+        Ok(result) => result,
+        Err(err) => fail(json, "Could not solve: ", &err.to_string(), ""),
+    };
     let end = Instant::now();
+
+    if json {
+        match result {
+            Some(result) => println!("{}", result.to_json()),
+            None => println!("null"),
+        }
+        return;
+    }
     println!("Solved in {:?}", end - start);
 
     if let Some(result) = result {
         if result.value == to_find {
             println!("Found an exact match:");
         } else {
-            println!("Found an approximate match:");
+            let distance = i64::from(result.value) - i64::from(to_find);
+            println!("Found an approximate match ({distance:+} from {to_find}):");
         }
-        println!("{}", result.as_text());
+        println!("{result}");
     } else {
         println!("Did not find a match");
     }
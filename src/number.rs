@@ -0,0 +1,540 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::expression::Expr;
+use crate::{Integer, Operation, Rules, SolveOptions, VerifyError};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+// A materialized operation (a + b) without the result
+pub struct MOperation<N = i32>(pub Operation, pub N, pub N);
+
+impl<N: Integer> MOperation<N> {
+    // The result of that operation
+    pub(crate) fn value(&self) -> N {
+        self.checked_value()
+            .expect("operations are checked when they are made")
+    }
+
+    // The result of that operation under the default rules of the game (see `Operation::apply`),
+    // None when it is not allowed there
+    pub fn evaluate(&self) -> Option<N> {
+        self.0.apply(self.1, self.2)
+    }
+
+    // The result of that operation, None if it can not be computed (eg: a division by 0)
+    // Only the arithmetic: a Number may come from other rules (eg: `SolveOptions::positive_only`)
+    fn checked_value(&self) -> Option<N> {
+        let (a, b) = (self.1, self.2);
+        match self.0 {
+            Operation::Addition => a.checked_add(b),
+            Operation::Multiplication => a.checked_mul(b),
+            Operation::Subtraction => a.checked_sub(b),
+            Operation::Division => a.checked_div(b),
+            Operation::Power => crate::options::power(a, b).ok(),
+            Operation::Modulo => a.checked_rem(b),
+            Operation::Concatenation => crate::options::concatenate(a, b).ok(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+// A single step of a calculus: lhs op rhs = result
+pub struct Step<N = i32> {
+    pub lhs: N,
+    pub op: Operation,
+    pub rhs: N,
+    pub result: N,
+}
+
+// A node of the operations tree of a Number
+// Operands that are not the input numbers point to the nodes of the Numbers they are,
+// so making a new Number from two others is a single allocation (no copy of their history)
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct OpNode<N> {
+    pub(crate) operation: MOperation<N>,
+    pub(crate) lhs: Option<Arc<OpNode<N>>>,
+    pub(crate) rhs: Option<Arc<OpNode<N>>>,
+}
+
+impl<N: Integer> OpNode<N> {
+    // The node of the first operation of `operations` (and its operands), with its result
+    // An operand is taken as the result of the next operation when their values match:
+    // if both operands have that value, the operation is given to the left one,
+    // which is the same calculus
+    fn from_preorder(operations: &mut &[MOperation<N>]) -> Option<(Self, N)> {
+        let (&operation, rest) = operations.split_first()?;
+        *operations = rest;
+
+        let lhs = Self::operand(operations, operation.1)?;
+        let rhs = Self::operand(operations, operation.2)?;
+        let node = OpNode {
+            operation,
+            lhs,
+            rhs,
+        };
+
+        Some((node, operation.checked_value()?))
+    }
+
+    // The node leading to `value`, if it is the next operation
+    fn operand(operations: &mut &[MOperation<N>], value: N) -> Option<Option<Arc<Self>>> {
+        match operations.first() {
+            Some(next) if next.checked_value() == Some(value) => {
+                let (node, _) = Self::from_preorder(operations)?;
+                Some(Some(Arc::new(node)))
+            }
+            _ => Some(None),
+        }
+    }
+
+    // The height of the tree under (and with) this node
+    fn depth(&self) -> usize {
+        let lhs = self.lhs.as_deref().map_or(0, Self::depth);
+        let rhs = self.rhs.as_deref().map_or(0, Self::depth);
+        1 + lhs.max(rhs)
+    }
+
+    // Append the operands that are input numbers, from left to right
+    fn tiles(&self, tiles: &mut Vec<N>) {
+        let MOperation(_, a, b) = self.operation;
+
+        for (child, operand) in [(&self.lhs, a), (&self.rhs, b)] {
+            match child {
+                Some(child) => child.tiles(tiles),
+                None => tiles.push(operand),
+            }
+        }
+    }
+
+    // The result of this operation, once its operands were checked
+    fn verify(&self, rules: &Rules) -> Result<N, VerifyError<N>> {
+        let MOperation(op, a, b) = self.operation;
+
+        for (child, operand) in [(&self.lhs, a), (&self.rhs, b)] {
+            if let Some(child) = child {
+                let value = child.verify(rules)?;
+                if value != operand {
+                    return Err(VerifyError::Mismatch {
+                        expected: value,
+                        found: operand,
+                    });
+                }
+            }
+        }
+
+        rules
+            .apply(op, a, b)
+            .map_err(|x| VerifyError::Forbidden(self.operation, x))
+    }
+}
+
+#[derive(Clone)]
+// Number with the operations that lead to it
+pub struct Number<N = i32> {
+    pub value: N,
+    len: usize,
+    operations: Option<Arc<OpNode<N>>>,
+}
+
+impl<N: Integer> Number<N> {
+    pub(crate) fn from_int(n: N) -> Self {
+        Self {
+            value: n,
+            len: 0,
+            operations: None,
+        }
+    }
+
+    // Rebuild a Number from its operations (as given by `operations()`)
+    // None if they do not make a single tree leading to `value`, or can not be computed
+    // The rules of the game are not checked (see `verify`)
+    #[cfg(any(feature = "serde", test))]
+    pub(crate) fn from_preorder(value: N, operations: &[MOperation<N>]) -> Option<Self> {
+        if operations.is_empty() {
+            return Some(Self::from_int(value));
+        }
+
+        let mut rest = operations;
+        let (node, result) = OpNode::from_preorder(&mut rest)?;
+
+        (rest.is_empty() && result == value).then(|| Self {
+            value,
+            len: operations.len(),
+            operations: Some(Arc::new(node)),
+        })
+    }
+
+    // Same as `from_preorder`, checking every operation with the default rules:
+    // the way to load a solution that can not be trusted (eg: from a file or a database)
+    pub fn from_operations(value: N, operations: &[MOperation<N>]) -> Result<Self, VerifyError<N>> {
+        let rules = Rules::default();
+        for operation in operations {
+            let MOperation(op, a, b) = *operation;
+            rules
+                .apply(op, a, b)
+                .map_err(|x| VerifyError::Forbidden(*operation, x))?;
+        }
+
+        if operations.is_empty() {
+            return Ok(Self::from_int(value));
+        }
+
+        let mut rest = operations;
+        let (node, result) =
+            OpNode::from_preorder(&mut rest).expect("the operations were checked above");
+
+        if let Some(unused) = rest.first() {
+            return Err(VerifyError::Unused(*unused));
+        }
+        if result != value {
+            return Err(VerifyError::Mismatch {
+                expected: result,
+                found: value,
+            });
+        }
+
+        Ok(Self {
+            value,
+            len: operations.len(),
+            operations: Some(Arc::new(node)),
+        })
+    }
+
+    pub(crate) fn from(value: N, op: Operation, a: &Number<N>, b: &Number<N>) -> Self {
+        let node = OpNode {
+            operation: MOperation(op, a.value, b.value),
+            lhs: a.operations.clone(),
+            rhs: b.operations.clone(),
+        };
+
+        Self {
+            value,
+            len: 1 + a.len + b.len,
+            operations: Some(Arc::new(node)),
+        }
+    }
+
+    // The length of a number is how many operations lead to it
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    // The height of the operations tree: 0 for an input number, otherwise the most
+    // operations from an input number to the value, eg: 2 for 2 * (50 + 25)
+    // Unlike the length, it tells a balanced tree ((a + b) * (c + d), 2)
+    // from a chain (((a + b) * c) + d, 3) with as many operations
+    pub fn depth(&self) -> usize {
+        self.tree().map_or(0, OpNode::depth)
+    }
+
+    // How many of the input numbers (tiles) are used, `tiles` gives which ones
+    // Every operation takes two numbers and gives back one, so that's always one more
+    // than the operations: the shortest Number is also the one using the fewest tiles
+    pub fn numbers_used(&self) -> usize {
+        self.len + 1
+    }
+
+    // The largest number written in the calculus, operands and results
+    // (the value itself for an input number), eg: to tell how ugly a solution is
+    pub fn max_intermediate(&self) -> N {
+        self.steps()
+            .iter()
+            .flat_map(|x| [x.lhs, x.rhs, x.result])
+            .fold(self.value, N::max)
+    }
+
+    // Same as `max_intermediate`, for the smallest number
+    pub fn min_intermediate(&self) -> N {
+        self.steps()
+            .iter()
+            .flat_map(|x| [x.lhs, x.rhs, x.result])
+            .fold(self.value, N::min)
+    }
+
+    // How many times each operation is used, eg: {+: 1, *: 1} for 2 * (50 + 25)
+    // Operations that are not used are left out (a Number without division has no `/`)
+    pub fn operation_counts(&self) -> BTreeMap<Operation, usize> {
+        let mut counts = BTreeMap::new();
+        for MOperation(operation, _, _) in self.operations() {
+            *counts.entry(operation).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    // The input numbers used (the leaves of the operations tree), from left to right,
+    // eg: [50, 25, 2] for (50 + 25) * 2, what use_all, require and fewest tiles build on
+    // A number given twice and used twice is there twice: `numbers_used` is its length
+    pub fn tiles(&self) -> Vec<N> {
+        let Some(tree) = self.tree() else {
+            return vec![self.value];
+        };
+
+        let mut tiles = Vec::with_capacity(self.numbers_used());
+        tree.tiles(&mut tiles);
+        tiles
+    }
+
+    // Whether every input number it uses is one of `available`, eg: the tiles of a puzzle
+    // A number used twice must be available twice
+    pub fn uses_valid_tiles(&self, available: &[N]) -> bool {
+        let mut available = available.to_vec();
+        self.tiles()
+            .iter()
+            .all(|x| match available.iter().position(|y| y == x) {
+                Some(i) => {
+                    available.swap_remove(i);
+                    true
+                }
+                None => false,
+            })
+    }
+
+    // The root of the operations tree (None for an input number)
+    pub(crate) fn tree(&self) -> Option<&OpNode<N>> {
+        self.operations.as_deref()
+    }
+
+    // The operations that lead to this Number: the last operation made,
+    // then the operations leading to its left operand, then to its right operand
+    pub fn operations(&self) -> Vec<MOperation<N>> {
+        let mut operations = Vec::with_capacity(self.len);
+        let mut stack: Vec<&OpNode<N>> = self.tree().into_iter().collect();
+
+        while let Some(node) = stack.pop() {
+            operations.push(node.operation);
+
+            // Right operand last, so the left one comes first
+            stack.extend(node.rhs.as_deref());
+            stack.extend(node.lhs.as_deref());
+        }
+
+        operations
+    }
+
+    // The steps of the calculus that lead to this Number, in the order they are made
+    pub fn steps(&self) -> Vec<Step<N>> {
+        self.operations()
+            .iter()
+            .rev()
+            .map(|op| Step {
+                lhs: op.1,
+                op: op.0,
+                rhs: op.2,
+                result: op.value(),
+            })
+            .collect()
+    }
+
+    // A text representation of the calculus that lead to this Number
+    pub fn as_text(&self) -> String {
+        self.to_string()
+    }
+
+    // The calculus as an infix expression with only the parentheses needed, eg: 2 * (50 + 25)
+    pub fn as_infix(&self) -> String {
+        Expr::from_number(self).as_infix()
+    }
+
+    // The operations as a tree, eg: Op { op: Multiplication, lhs: Value(2), rhs: Op { … } }
+    pub fn to_expr(&self) -> Expr<N> {
+        Expr::from_number(self)
+    }
+
+    // The calculus as a single infix expression, eg: ((50 + 25) * 2)
+    // Every operation is enclosed in parentheses, bare numbers are not
+    pub fn as_expression(&self) -> String {
+        Expr::from_number(self).as_expression()
+    }
+
+    // Check that every operation is allowed by the (default) rules,
+    // and that they do lead to the value of this Number
+    pub fn verify(&self) -> Result<(), VerifyError<N>> {
+        self.check(&Rules::default())
+    }
+
+    // Same as `verify`, with the rules of `options`
+    pub fn verify_with(&self, options: &SolveOptions) -> Result<(), VerifyError<N>> {
+        self.check(&options.rules())
+    }
+
+    fn check(&self, rules: &Rules) -> Result<(), VerifyError<N>> {
+        if let Some(node) = self.tree() {
+            let value = node.verify(rules)?;
+            if value != self.value {
+                return Err(VerifyError::Mismatch {
+                    expected: value,
+                    found: self.value,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // The calculus in reverse polish notation, eg: 50 25 + 2 *
+    pub fn as_rpn(&self) -> String {
+        Expr::from_number(self).as_rpn()
+    }
+
+    // The calculus as a LaTeX math expression, divisions being fractions,
+    // eg: 2 \times (50 + 25)
+    pub fn to_latex(&self) -> String {
+        Expr::from_number(self).as_latex()
+    }
+
+    // The operations tree as a GraphViz digraph (eg: for `dot -Tpng`)
+    // Input numbers are boxes, operations are labeled with their result
+    pub fn to_dot(&self) -> String {
+        Expr::from_number(self).as_dot()
+    }
+
+    // A key equal for every calculus that only differs by the order of the operands
+    // of additions and multiplications, eg: (2 + (50 + 25)) and ((25 + 50) + 2)
+    // Sums and products are flattened, their operands sorted in string order: (2 + 25 + 50)
+    pub fn canonical_key(&self) -> String {
+        Expr::from_number(self).canonical_key()
+    }
+
+    // Order by complexity, simplest first, eg: solutions.sort_by(Number::cmp_by_complexity)
+    // Fewer operations first, then by canonical key, then by value: this is not the order
+    // of the values (sort by `value` for that), and not an `Ord` since two different
+    // calculations are equal only when they are the same up to commutativity
+    pub fn cmp_by_complexity(&self, other: &Self) -> Ordering {
+        self.len
+            .cmp(&other.len)
+            .then_with(|| self.canonical_key().cmp(&other.canonical_key()))
+            .then_with(|| self.value.cmp(&other.value))
+    }
+}
+
+// The fields of a serialized Number, shared by the `serde` serialization and `to_json`
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename = "Number"))]
+struct Fields<N> {
+    value: N,
+    operations: Vec<MOperation<N>>,
+    max_intermediate: N,
+    min_intermediate: N,
+    operation_counts: BTreeMap<Operation, usize>,
+}
+
+impl<N: Integer> Number<N> {
+    fn fields(&self) -> Fields<N> {
+        Fields {
+            value: self.value,
+            operations: self.operations(),
+            max_intermediate: self.max_intermediate(),
+            min_intermediate: self.min_intermediate(),
+            operation_counts: self.operation_counts(),
+        }
+    }
+}
+
+impl<N: Integer + Into<i128>> Number<N> {
+    // The same JSON as the `serde` serialization, without the feature (eg: for the command line)
+    pub fn to_json(&self) -> String {
+        let Fields {
+            value,
+            operations,
+            max_intermediate,
+            min_intermediate,
+            operation_counts,
+        } = self.fields();
+        let operations: Vec<String> = operations
+            .into_iter()
+            .map(|MOperation(op, a, b)| format!("[\"{op}\",{},{}]", a.into(), b.into()))
+            .collect();
+        let counts: Vec<String> = operation_counts
+            .iter()
+            .map(|(op, count)| format!("\"{op}\":{count}"))
+            .collect();
+
+        format!(
+            "{{\"value\":{},\"operations\":[{}],\"max_intermediate\":{},\"min_intermediate\":{},\"operation_counts\":{{{}}}}}",
+            value.into(),
+            operations.join(","),
+            max_intermediate.into(),
+            min_intermediate.into(),
+            counts.join(",")
+        )
+    }
+}
+
+// Serialized with the flat list of operations (as `operations()`), eg:
+// {"value": 150, "operations": [["*", 2, 75], ["+", 50, 25]],
+//  "max_intermediate": 150, "min_intermediate": 2, "operation_counts": {"+": 1, "*": 1}}
+// The intermediates and counts are only given for display, and ignored when deserialized
+#[cfg(feature = "serde")]
+impl<N: Integer + Serialize> Serialize for Number<N> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.fields().serialize(serializer)
+    }
+}
+
+// One line per step of the calculus
+impl<N: Integer> std::fmt::Display for Number<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (i, step) in self.steps().iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{step}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<N: Integer> std::fmt::Display for Step<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {} {} = {}", self.lhs, self.op, self.rhs, self.result)
+    }
+}
+
+// A Number compared (and hashed) by its value only, eg: to keep one Number per value
+// in a HashSet, like the search does
+// Two Numbers with the same value are equal, even with different operations
+#[derive(Clone, Debug)]
+pub struct ByValue<N: Integer = i32>(pub Number<N>);
+
+impl<N: Integer> PartialEq for ByValue<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.value == other.0.value
+    }
+}
+
+impl<N: Integer> Eq for ByValue<N> {}
+
+impl<N: Integer> Hash for ByValue<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.value.hash(state);
+    }
+}
+
+// Only show the value
+impl<N: Integer> std::fmt::Debug for Number<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+// Rebuilt from the flat list of operations, which must lead to the value
+// The rules of the game are not checked: use `verify` on untrusted data
+#[cfg(feature = "serde")]
+impl<'de, N: Integer + Deserialize<'de>> Deserialize<'de> for Number<N> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Flat<N> {
+            value: N,
+            operations: Vec<MOperation<N>>,
+        }
+
+        let flat = Flat::deserialize(deserializer)?;
+        Number::from_preorder(flat.value, &flat.operations)
+            .ok_or_else(|| serde::de::Error::custom("the operations do not lead to the value"))
+    }
+}
@@ -0,0 +1,346 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use crate::{
+    Integer, Number, Operation, OperationSet, Violation, MAX_EXPONENT, MAX_TILES, MAX_WORKERS,
+};
+
+// Configuration of a solve, built with chained calls:
+// SolveOptions::new().approximation(3).max_workers(4).build()
+// `N` is the type of the numbers searched (see `score_with`), i32 for the game
+#[derive(Clone, Debug)]
+pub struct SolveOptions<N = i32> {
+    pub(crate) approximation: i32,
+    pub(crate) max_workers: usize,
+    pub(crate) max_tiles: usize,
+    pub(crate) operations: OperationSet,
+    pub(crate) channel_capacity: Option<usize>,
+    pub(crate) stop: Option<Arc<AtomicBool>>,
+    pub(crate) prune: bool,
+    pub(crate) fractions: bool,
+    pub(crate) positive_only: bool,
+    pub(crate) zero: bool,
+    pub(crate) concatenation: bool,
+    pub(crate) reuse: bool,
+    pub(crate) max_operations: Option<usize>,
+    pub(crate) use_all: bool,
+    pub(crate) required: Vec<i32>,
+    pub(crate) selection: SelectionPolicy,
+    pub(crate) score: Option<Scorer<N>>,
+}
+
+// A scoring function given to `SolveOptions::score_with`
+pub(crate) struct Scorer<N>(pub(crate) Arc<ScoreFn<N>>);
+
+type ScoreFn<N> = dyn Fn(&Number<N>) -> i64 + Send + Sync;
+
+impl<N> Clone for Scorer<N> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<N> std::fmt::Debug for Scorer<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Scorer")
+    }
+}
+
+// Which Number to keep when a value is reached in several ways (see `SolveOptions::selection`)
+// There is no policy for the fewest input numbers: that's always the fewest operations
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionPolicy {
+    // The shortest Number
+    #[default]
+    FewestOperations,
+    // The Number with the smallest `Number::max_intermediate` (then the shortest one)
+    SmallestIntermediates,
+    // The Number using the fewest kinds of operations (`Number::operation_counts`),
+    // then the shortest one, eg: 100 - 10 - 8 rather than 2 + 8 * 10
+    FewestOperationKinds,
+}
+
+// The rules to combine numbers (from the options), used by the workers
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Rules {
+    pub(crate) operations: OperationSet,
+    pub(crate) prune: bool,
+    pub(crate) fractions: bool,
+    pub(crate) positive_only: bool,
+    pub(crate) zero: bool,
+    pub(crate) concatenation: bool,
+    pub(crate) reuse: bool,
+    // Whether some numbers are required: Numbers of the same value are then not the same
+    pub(crate) required: bool,
+}
+
+impl<N> Default for SolveOptions<N> {
+    fn default() -> Self {
+        Self {
+            approximation: 0,
+            max_workers: MAX_WORKERS,
+            max_tiles: MAX_TILES,
+            operations: OperationSet::ALL,
+            channel_capacity: None,
+            stop: None,
+            prune: true,
+            fractions: false,
+            positive_only: true,
+            zero: false,
+            concatenation: false,
+            reuse: false,
+            max_operations: None,
+            use_all: false,
+            required: Vec::new(),
+            selection: SelectionPolicy::default(),
+            score: None,
+        }
+    }
+}
+
+impl<N: Integer> SolveOptions<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Accept results up to n away from the target
+    pub fn approximation(mut self, approximation: i32) -> Self {
+        self.approximation = approximation;
+        self
+    }
+
+    // Number of combination workers, 0 (or 1) means single threaded
+    pub fn max_workers(mut self, max_workers: usize) -> Self {
+        self.max_workers = max_workers;
+        self
+    }
+
+    // Most numbers to combine, 8 by default (more is an error)
+    // Every extra number multiplies the time and memory the search takes, 9 or more
+    // can take minutes and gigabytes: only raise this for inputs that can be trusted
+    pub fn max_tiles(mut self, max_tiles: usize) -> Self {
+        self.max_tiles = max_tiles;
+        self
+    }
+
+    // Only combine numbers using those operations
+    // Adding `Operation::Power` makes the search much longer: two more operations to try
+    // on every pair, and values far larger than the tiles (eg: 100 ^ 4), which go on
+    // to be combined with the others. Expect several times the time and memory
+    // Adding `Operation::Modulo` changes which targets are reachable too: small values
+    // come from large ones (eg: 100 % 7 = 2), and can then be used in other operations
+    pub fn operations(mut self, operations: OperationSet) -> Self {
+        self.operations = operations;
+        self
+    }
+
+    // Use bounded channels between the combination workers (unbounded by default)
+    // Producers then wait instead of queuing, which caps the memory used.
+    // This has no effect on the single threaded search
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = Some(capacity);
+        self
+    }
+
+    // Stop the search as soon as this flag is set (from any thread)
+    // What was found until then is still returned
+    pub fn stop(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.stop = Some(flag);
+        self
+    }
+
+    // Skip operations giving back one of their operands (a * 1, a / 1, …), on by default
+    // They can not lead to anything new, so this only matters to get an exhaustive tree
+    pub fn prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
+    }
+
+    // Accept any division (not only the exact ones), off by default
+    // This needs a type able to represent fractions, eg: all_combinations_with::<Rational>
+    // Intermediate values may then be fractions, but only whole results are kept
+    pub fn fractions(mut self, fractions: bool) -> Self {
+        self.fractions = fractions;
+        self
+    }
+
+    // Only accept strictly positive results, for every operation, on by default
+    // Off, subtractions can give zero or negative values, divisions by a negative
+    // number are accepted (divisions still have to be exact), and so on
+    pub fn positive_only(mut self, positive_only: bool) -> Self {
+        self.positive_only = positive_only;
+        self
+    }
+
+    // Accept any subtraction (zero and negative results too), off by default
+    // The same as `positive_only(!negatives)`
+    pub fn negatives(mut self, negatives: bool) -> Self {
+        self.positive_only = !negatives;
+        self
+    }
+
+    // Accept results of exactly 0 (eg: a - b >= 0 instead of a - b > 0), off by default
+    // Zero can not help to reach anything else (a + 0, a * 0, … are pruned),
+    // but it can then be found as a target
+    pub fn zero(mut self, zero: bool) -> Self {
+        self.zero = zero;
+        self
+    }
+
+    // Also combine two input numbers by writing them next to each other
+    // (`Operation::Concatenation`, eg: 2 and 5 make 25), off by default
+    // Only input numbers are concatenated, never results, but every pair of tiles gives
+    // two more values to combine with the others: the search takes much longer
+    pub fn concatenation(mut self, concatenation: bool) -> Self {
+        self.concatenation = concatenation;
+        self
+    }
+
+    // Let input numbers be used more than once, off by default (eg: 100 * 100 from one 100)
+    // Input numbers are then never consumed, only the results of operations are:
+    // the lists of numbers no longer get shorter, so the search stops once Numbers have
+    // as many operations as they could without reuse (one less than the input numbers)
+    // Even so, far more values are reachable, and the search takes much longer
+    pub fn allow_reuse(mut self, reuse: bool) -> Self {
+        self.reuse = reuse;
+        self
+    }
+
+    // Only make Numbers with at most n operations (any number of operations by default)
+    // The search stops after n levels (each adds an operation), so it is also shorter:
+    // a target needing more operations is not found, or only approximated
+    pub fn max_operations(mut self, max_operations: usize) -> Self {
+        self.max_operations = Some(max_operations);
+        self
+    }
+
+    // Only keep Numbers using every input number (each exactly once), off by default
+    // This turns pruning off: a * 1 is then a legitimate way to use the 1
+    pub fn use_all(mut self, use_all: bool) -> Self {
+        self.use_all = use_all;
+        self
+    }
+
+    // Only keep Numbers using those input numbers (as many times as they are given here)
+    // Among them, the shortest Number of each value is still the one kept
+    // Like `use_all`, this turns pruning off (100 * 1 is a way to use the 1), and the
+    // search keeps apart the lists of values that used different required numbers:
+    // it goes through more lists, and takes longer
+    pub fn require(mut self, numbers: &[i32]) -> Self {
+        self.required = numbers.to_vec();
+        self
+    }
+
+    // Which Number to keep for each value, the shortest by default
+    // The search only explores one way to reach each list of intermediate values
+    // (the shortest), so another policy chooses among the Numbers made from those
+    pub fn selection(mut self, selection: SelectionPolicy) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    // Keep the Number with the lowest `score` for each value (then the shortest one),
+    // instead of following the `selection` policy
+    // The default is the same as scoring the number of operations, and the policies can be
+    // written as well, eg: score_with(|x| x.max_intermediate() as i64) for the smallest
+    // intermediates
+    pub fn score_with(mut self, score: impl Fn(&Number<N>) -> i64 + Send + Sync + 'static) -> Self {
+        self.score = Some(Scorer(Arc::new(score)));
+        self
+    }
+
+    pub fn build(self) -> Self {
+        self
+    }
+
+    pub(crate) fn rules(&self) -> Rules {
+        Rules {
+            operations: self.operations,
+            prune: self.prune && !self.use_all && self.required.is_empty(),
+            fractions: self.fractions,
+            positive_only: self.positive_only,
+            zero: self.zero,
+            concatenation: self.concatenation,
+            reuse: self.reuse,
+            required: !self.required.is_empty(),
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        SolveOptions::<i32>::default().rules()
+    }
+}
+
+impl Rules {
+    // The result of a single operation, or the rule it breaks
+    // (whether the operation itself is allowed is not checked here)
+    pub(crate) fn apply<N: Integer>(
+        &self,
+        operation: Operation,
+        a: N,
+        b: N,
+    ) -> Result<N, Violation> {
+        let value = match operation {
+            Operation::Addition => a.checked_add(b).ok_or(Violation::Overflow),
+            Operation::Multiplication => a.checked_mul(b).ok_or(Violation::Overflow),
+            Operation::Subtraction => a.checked_sub(b).ok_or(Violation::Overflow),
+            Operation::Division if b == N::ZERO => Err(Violation::DivisionByZero),
+            // Any quotient the type can represent (exactly)
+            Operation::Division if self.fractions => {
+                let value = a.checked_div(b).ok_or(Violation::Overflow)?;
+                match value.checked_mul(b) {
+                    Some(x) if x == a => Ok(value),
+                    _ => Err(Violation::NotInteger),
+                }
+            }
+            Operation::Division => match a.checked_rem(b) {
+                Some(x) if x == N::ZERO => a.checked_div(b).ok_or(Violation::Overflow),
+                _ => Err(Violation::NotInteger),
+            },
+            Operation::Power => power(a, b),
+            Operation::Modulo if b == N::ZERO => Err(Violation::DivisionByZero),
+            Operation::Modulo if b < N::ZERO => Err(Violation::NotPositive),
+            Operation::Modulo => a.checked_rem(b).ok_or(Violation::Overflow),
+            Operation::Concatenation => concatenate(a, b),
+        }?;
+
+        // The sign of every result is only checked here
+        if !self.positive_only || value > N::ZERO || (self.zero && value == N::ZERO) {
+            Ok(value)
+        } else {
+            Err(Violation::NotPositive)
+        }
+    }
+}
+
+// The digits of a then the digits of b, eg: 25 for 2 and 5 (or 20 for 2 and 0)
+pub(crate) fn concatenate<N: Integer>(a: N, b: N) -> Result<N, Violation> {
+    if a < N::ZERO || b < N::ZERO {
+        return Err(Violation::NotPositive);
+    }
+    let ten = N::try_from(10).map_err(|_| Violation::Overflow)?;
+
+    // The power of 10 above b
+    let mut shift = ten;
+    while shift <= b {
+        shift = shift.checked_mul(ten).ok_or(Violation::Overflow)?;
+    }
+
+    a.checked_mul(shift)
+        .and_then(|x| x.checked_add(b))
+        .ok_or(Violation::Overflow)
+}
+
+// a ^ b by repeated multiplications, for an exponent from 0 to MAX_EXPONENT
+pub(crate) fn power<N: Integer>(a: N, b: N) -> Result<N, Violation> {
+    let exponent = (0..=MAX_EXPONENT)
+        .find(|x| N::try_from(*x).ok() == Some(b))
+        .ok_or(Violation::Exponent)?;
+    let one = N::try_from(1).map_err(|_| Violation::Overflow)?;
+
+    (0..exponent)
+        .try_fold(one, |x, _| x.checked_mul(a))
+        .ok_or(Violation::Overflow)
+}
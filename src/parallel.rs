@@ -0,0 +1,76 @@
+use crossbeam_channel::unbounded;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+use crate::{
+    check_tiles, combine, is_smaller, keep_shortest, Integer, Number, ResultSet, Rules, SieveKey,
+    SolveError, MAX_TILES,
+};
+
+// Same as `all_combinations`, using rayon to combine every list of a level in parallel
+// (the number of threads is rayon's, eg: RAYON_NUM_THREADS)
+pub fn all_combinations_rayon<N: Integer>(base_numbers: &[N]) -> Result<ResultSet<N>, SolveError> {
+    check_tiles(base_numbers.len(), MAX_TILES)?;
+
+    let initial: Vec<Number<N>> = base_numbers.iter().map(|x| Number::from_int(*x)).collect();
+
+    let mut results: ResultSet<N> = HashMap::with_capacity(500);
+    for number in &initial {
+        keep_shortest(&mut results, number.clone());
+    }
+
+    let mut level = vec![initial];
+    while !level.is_empty() {
+        let sieve = DashMap::with_capacity(500);
+
+        let found = level
+            .into_par_iter()
+            .map(|elements| combine_into(&elements, &sieve))
+            .try_reduce(HashMap::new, |mut a, b| {
+                for x in b.into_values() {
+                    keep_shortest(&mut a, x);
+                }
+                Ok(a)
+            })?;
+
+        for x in found.into_values() {
+            keep_shortest(&mut results, x);
+        }
+        level = sieve.into_iter().map(|(_, elements)| elements).collect();
+    }
+
+    Ok(results)
+}
+
+// Combine a single list of Numbers, adding the next lists to the (shared) sieve
+// and returning the shortest Number of each value found
+fn combine_into<N: Integer>(
+    elements: &[Number<N>],
+    sieve: &DashMap<SieveKey<N>, Vec<Number<N>>>,
+) -> Result<ResultSet<N>, SolveError> {
+    let (tx, rx) = unbounded();
+    let (result_tx, result_rx) = unbounded();
+
+    combine(tx, elements, result_tx, Rules::default())?;
+
+    for next in rx {
+        match sieve.entry(SieveKey::new(&next)) {
+            Entry::Occupied(mut entry) => {
+                if is_smaller(&next, entry.get()) {
+                    entry.insert(next);
+                }
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(next);
+            }
+        }
+    }
+
+    let mut results = HashMap::new();
+    for value in result_rx {
+        keep_shortest(&mut results, value);
+    }
+    Ok(results)
+}
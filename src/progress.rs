@@ -0,0 +1,33 @@
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+// How often (in computed Numbers) the progress callback is called during a level
+pub(crate) const PROGRESS_INTERVAL: usize = 10_000;
+
+// Counters of a running search, given to the progress callback
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Progress {
+    // Number of operations made so far (a level of the search)
+    pub level: usize,
+    // Lists of numbers that were combined
+    pub combined: usize,
+    // Distinct lists of numbers seen by the sieve
+    pub states: usize,
+    // Computed Numbers (the same value is usually found many times)
+    pub results: usize,
+}
+
+// Totals of a whole search (see `all_combinations_stats`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SearchStats {
+    // Operations tried on a pair of numbers
+    pub attempted: usize,
+    // Operations allowed by the rules (each one is a computed Number)
+    pub succeeded: usize,
+    // Distinct lists of numbers kept by the sieve
+    pub states: usize,
+    // Most messages waiting in a single channel, sampled as results arrive
+    pub peak_channel_depth: usize,
+}
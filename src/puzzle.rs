@@ -0,0 +1,252 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use crate::{all_combinations, solve_all, Number, PuzzleError, SolveError, TileError, MAX_WORKERS};
+
+// The classic tiles: two of each small number, one of each large number
+const SMALL_TILES: [i32; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+const LARGE_TILES: [i32; 4] = [25, 50, 75, 100];
+const TILES: usize = 6;
+
+// Targets are drawn in this range
+const TARGETS: RangeInclusive<i32> = 100..=999;
+
+// Difficulty thresholds (see `difficulty`)
+const TRIVIAL_OPERATIONS: usize = 1;
+const EASY_OPERATIONS: usize = 2;
+const HARD_OPERATIONS: usize = 4;
+const HARD_SOLUTIONS: usize = 10;
+const FEW_SOLUTIONS: usize = 2;
+
+// A source of random numbers for the puzzle generator
+pub trait RandomSource {
+    fn next_u64(&mut self) -> u64;
+
+    // A number in 0..n (n > 0)
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+// A small seeded generator (SplitMix64): the same seed always gives the same numbers,
+// on any platform and with any version of the crate, eg: for a puzzle of the day
+#[derive(Clone, Debug)]
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+}
+
+impl RandomSource for SeededRng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+// Which tiles can be used in a game (see `validate_tiles_with`)
+// The default is the classic game: 6 tiles, each small one at most twice, each large one once
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TileRules {
+    pub small: Vec<i32>,
+    pub small_copies: usize,
+    pub large: Vec<i32>,
+    pub large_copies: usize,
+    // How many tiles a game has (None for any number of them)
+    pub count: Option<usize>,
+}
+
+impl Default for TileRules {
+    fn default() -> Self {
+        Self {
+            small: SMALL_TILES.to_vec(),
+            small_copies: 2,
+            large: LARGE_TILES.to_vec(),
+            large_copies: 1,
+            count: Some(TILES),
+        }
+    }
+}
+
+// Check a selection of tiles against the classic rules (see `TileRules`),
+// eg: before solving numbers given by a player
+pub fn validate_tiles(numbers: &[i32]) -> Result<(), TileError> {
+    validate_tiles_with(numbers, &TileRules::default())
+}
+
+// Same as `validate_tiles`, with other rules
+pub fn validate_tiles_with(numbers: &[i32], rules: &TileRules) -> Result<(), TileError> {
+    if let Some(count) = rules.count.filter(|x| *x != numbers.len()) {
+        return Err(TileError::Count {
+            expected: count,
+            found: numbers.len(),
+        });
+    }
+
+    for (i, tile) in numbers.iter().enumerate() {
+        let max = if rules.large.contains(tile) {
+            rules.large_copies
+        } else if rules.small.contains(tile) {
+            rules.small_copies
+        } else {
+            return Err(TileError::Unknown(*tile));
+        };
+
+        // Reported once there is one too many
+        let copies = numbers[..=i].iter().filter(|x| *x == tile).count();
+        if copies > max {
+            return Err(TileError::TooMany { tile: *tile, max });
+        }
+    }
+
+    Ok(())
+}
+
+// A game: find `target` with `numbers`, `solution` being the shortest way to do it
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    pub numbers: Vec<i32>,
+    pub target: i32,
+    pub solution: Number,
+}
+
+// Draw `count` tiles from `pool` (each tile at most once)
+fn draw<R: RandomSource>(rng: &mut R, mut pool: Vec<i32>, count: usize) -> Vec<i32> {
+    (0..count)
+        .map(|_| pool.swap_remove(rng.below(pool.len())))
+        .collect()
+}
+
+// A random puzzle with the classic tiles: `large_count` of 25, 50, 75 and 100,
+// and `small_count` from 1 to 10 (two of each), 6 tiles in total
+// The target (from 100 to 999) is picked among the reachable ones, so there is always a solution
+// Nothing else is random: the same `rng` state (eg: `SeededRng::new` with the same seed)
+// and the same counts always give the same tiles, target and solution
+pub fn generate_puzzle<R: RandomSource>(
+    rng: &mut R,
+    large_count: usize,
+    small_count: usize,
+) -> Result<Puzzle, PuzzleError> {
+    if large_count > LARGE_TILES.len() {
+        return Err(PuzzleError::TooManyLarge(large_count));
+    }
+    if large_count + small_count != TILES {
+        return Err(PuzzleError::TileCount(large_count + small_count));
+    }
+
+    let small = SMALL_TILES.iter().flat_map(|x| [*x, *x]).collect();
+    let mut numbers = draw(rng, LARGE_TILES.to_vec(), large_count);
+    numbers.extend(draw(rng, small, small_count));
+
+    let mut results = all_combinations(&numbers, MAX_WORKERS)?;
+
+    // Sorted, so the same random numbers always give the same target
+    let mut targets: Vec<i32> = results
+        .keys()
+        .filter(|x| TARGETS.contains(x))
+        .copied()
+        .collect();
+    targets.sort_unstable();
+
+    if targets.is_empty() {
+        return Err(PuzzleError::NoTarget(numbers));
+    }
+    let target = targets[rng.below(targets.len())];
+
+    let solution = results
+        .remove(&target)
+        .ok_or_else(|| SolveError::Internal(format!("{target} is not in the results")))?;
+
+    Ok(Puzzle {
+        numbers,
+        target,
+        solution,
+    })
+}
+
+// How hard it is to find a target (see `difficulty`), from the easiest to the hardest
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Difficulty {
+    Trivial,
+    Easy,
+    Medium,
+    Hard,
+    // No exact solution (there may still be approximate ones)
+    Unsolvable,
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let label = match self {
+            Difficulty::Trivial => "Trivial",
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Unsolvable => "Unsolvable",
+        };
+        write!(f, "{label}")
+    }
+}
+
+// Rate a puzzle, from its shortest solution and its number of distinct solutions (`solve_all`):
+// - Unsolvable: there is no exact solution (approximate ones do not count)
+// - Trivial: the target is a tile, or a single operation away
+// - Easy: a solution with 2 operations
+// - Hard: at most 2 solutions, or at most 10 solutions needing at least 4 operations
+// - Medium: everything else
+// For [5, 25, 2, 50, 100, 10]: 250 is Trivial (10 * 25), 252 is Easy (5 * 50 + 2),
+// 997 is Medium (16 solutions of 3 operations), 281 is Hard (a single solution
+// of 5 operations) and 831 is Unsolvable
+pub fn difficulty(base_numbers: &[i32], target: i32) -> Result<Difficulty, SolveError> {
+    let solutions = solve_all(base_numbers, target)?;
+
+    let difficulty = match solutions.first() {
+        None => Difficulty::Unsolvable,
+        Some(shortest) if shortest.len() <= TRIVIAL_OPERATIONS => Difficulty::Trivial,
+        Some(shortest) if shortest.len() <= EASY_OPERATIONS => Difficulty::Easy,
+        Some(_) if solutions.len() <= FEW_SOLUTIONS => Difficulty::Hard,
+        Some(shortest)
+            if shortest.len() >= HARD_OPERATIONS && solutions.len() <= HARD_SOLUTIONS =>
+        {
+            Difficulty::Hard
+        }
+        Some(_) => Difficulty::Medium,
+    };
+
+    Ok(difficulty)
+}
+
+// The shortest solution of every reachable target of `range`, in increasing order
+pub fn solvable_targets(
+    base_numbers: &[i32],
+    range: RangeInclusive<i32>,
+) -> Result<BTreeMap<i32, Number>, SolveError> {
+    let results = all_combinations(base_numbers, MAX_WORKERS)?;
+
+    Ok(results
+        .into_iter()
+        .filter(|(value, _)| range.contains(value))
+        .collect())
+}
+
+// The reachable target of `range` with the longest shortest solution (the smallest one on ties)
+// All targets come from a single search
+pub fn hardest_target(
+    base_numbers: &[i32],
+    range: RangeInclusive<i32>,
+) -> Result<Option<(i32, Number)>, SolveError> {
+    let results = all_combinations(base_numbers, MAX_WORKERS)?;
+
+    let hardest = results
+        .into_iter()
+        .filter(|(value, _)| range.contains(value))
+        .max_by_key(|(value, number)| (number.len(), Reverse(*value)));
+
+    Ok(hardest)
+}
@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+
+use crate::Integer;
+
+// A fraction, always reduced (with a positive denominator), eg: 8/3
+// Used as the Integer type of a search, it allows non-integer intermediate values
+// (with `SolveOptions::fractions`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: i64,
+    denominator: i64,
+}
+
+// Computed on the absolute values, as -i64::MIN does not fit in an i64
+fn gcd(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl Rational {
+    // None for a zero denominator (or when the reduced fraction does not fit)
+    pub fn new(numerator: i64, denominator: i64) -> Option<Self> {
+        if denominator == 0 {
+            return None;
+        }
+
+        // Reduced in i128: the divisor may be 2^63, and the signs may have to be swapped
+        let divisor = i128::from(gcd(numerator, denominator));
+        let (mut numerator, mut denominator) = (
+            i128::from(numerator) / divisor,
+            i128::from(denominator) / divisor,
+        );
+        if denominator < 0 {
+            (numerator, denominator) = (-numerator, -denominator);
+        }
+
+        Some(Self {
+            numerator: i64::try_from(numerator).ok()?,
+            denominator: i64::try_from(denominator).ok()?,
+        })
+    }
+
+    pub fn numerator(&self) -> i64 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> i64 {
+        self.denominator
+    }
+
+    // The value as an integer, when it is one
+    pub fn to_integer(&self) -> Option<i64> {
+        (self.denominator == 1).then_some(self.numerator)
+    }
+}
+
+impl From<i32> for Rational {
+    fn from(n: i32) -> Self {
+        Self::from(i64::from(n))
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(n: i64) -> Self {
+        Self {
+            numerator: n,
+            denominator: 1,
+        }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Denominators are positive, and the products always fit in an i128
+        let lhs = i128::from(self.numerator) * i128::from(other.denominator);
+        let rhs = i128::from(other.numerator) * i128::from(self.denominator);
+        lhs.cmp(&rhs)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Integer for Rational {
+    const ZERO: Self = Self {
+        numerator: 0,
+        denominator: 1,
+    };
+
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        let numerator = self
+            .numerator
+            .checked_mul(rhs.denominator)?
+            .checked_add(rhs.numerator.checked_mul(self.denominator)?)?;
+        Self::new(numerator, self.denominator.checked_mul(rhs.denominator)?)
+    }
+
+    fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.checked_add(Self::new(rhs.numerator.checked_neg()?, rhs.denominator)?)
+    }
+
+    fn checked_mul(self, rhs: Self) -> Option<Self> {
+        Self::new(
+            self.numerator.checked_mul(rhs.numerator)?,
+            self.denominator.checked_mul(rhs.denominator)?,
+        )
+    }
+
+    // Always exact (None only for a division by zero)
+    fn checked_div(self, rhs: Self) -> Option<Self> {
+        Self::new(
+            self.numerator.checked_mul(rhs.denominator)?,
+            self.denominator.checked_mul(rhs.numerator)?,
+        )
+    }
+
+    // What is left once `rhs` is taken a whole number of times (rounded down), eg: 7/2 % 1 = 1/2
+    fn checked_rem(self, rhs: Self) -> Option<Self> {
+        let quotient = self.checked_div(rhs)?;
+        let whole = Self::from(quotient.numerator.div_euclid(quotient.denominator));
+        self.checked_sub(rhs.checked_mul(whole)?)
+    }
+
+    fn is_integer(self) -> bool {
+        self.denominator == 1
+    }
+}
+
+// As an integer when it is one, eg: 8 or 8/3
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.to_integer() {
+            Some(n) => write!(f, "{n}"),
+            None => write!(f, "{}/{}", self.numerator, self.denominator),
+        }
+    }
+}
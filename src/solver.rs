@@ -0,0 +1,125 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{all_combinations, closest_in, Number, ResultSet, SolveError, MAX_WORKERS};
+
+// How many tile sets a Solver remembers by default
+const DEFAULT_CAPACITY: usize = 64;
+
+// Solves puzzles, remembering the results of the tile sets it combined last
+// The order of the numbers does not matter: [2, 50, 25] uses the results of [25, 50, 2]
+// - on a hit, the target is looked up in the remembered results, without any search
+// - on a miss, `all_combinations` runs (without holding the cache, so other threads
+//   are not blocked meanwhile), then its results replace the least recently used ones
+//   when the cache is full
+// A Solver can be shared between threads (eg: behind an Arc); two threads missing
+// the same tile set at the same time both run the search
+pub struct Solver {
+    capacity: usize,
+    cache: Mutex<Cache>,
+}
+
+#[derive(Default)]
+struct Cache {
+    results: HashMap<Vec<i32>, Arc<ResultSet>>,
+    // Tile sets from the least to the most recently used
+    order: VecDeque<Vec<i32>>,
+}
+
+impl Cache {
+    fn get(&mut self, tiles: &[i32]) -> Option<Arc<ResultSet>> {
+        let results = self.results.get(tiles)?.clone();
+        self.touch(tiles);
+        Some(results)
+    }
+
+    // Make `tiles` the most recently used set
+    fn touch(&mut self, tiles: &[i32]) {
+        if let Some(i) = self.order.iter().position(|x| x == tiles) {
+            if let Some(tiles) = self.order.remove(i) {
+                self.order.push_back(tiles);
+            }
+        }
+    }
+
+    fn insert(&mut self, tiles: Vec<i32>, results: Arc<ResultSet>, capacity: usize) {
+        if self.results.contains_key(&tiles) {
+            self.touch(&tiles);
+            return;
+        }
+
+        while self.order.len() >= capacity {
+            match self.order.pop_front() {
+                Some(oldest) => self.results.remove(&oldest),
+                None => return,
+            };
+        }
+
+        self.order.push_back(tiles.clone());
+        self.results.insert(tiles, results);
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver {
+    // A Solver remembering up to 64 tile sets
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    // A Solver remembering up to `capacity` tile sets (0 never remembers anything)
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    // Same as `solve`
+    pub fn solve(
+        &self,
+        base_numbers: &[i32],
+        to_find: i32,
+        approximation: i32,
+    ) -> Result<Option<Number>, SolveError> {
+        let results = self.results(base_numbers)?;
+        Ok(closest_in(&results, to_find, approximation))
+    }
+
+    // How many tile sets are remembered
+    pub fn len(&self) -> usize {
+        self.lock().map_or(0, |x| x.results.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The results for a tile set, from the cache when possible
+    fn results(&self, base_numbers: &[i32]) -> Result<Arc<ResultSet>, SolveError> {
+        let mut tiles = base_numbers.to_vec();
+        tiles.sort_unstable();
+
+        if let Some(results) = self.lock()?.get(&tiles) {
+            return Ok(results);
+        }
+
+        let results = Arc::new(all_combinations(&tiles, MAX_WORKERS)?);
+        if self.capacity > 0 {
+            self.lock()?.insert(tiles, results.clone(), self.capacity);
+        }
+
+        Ok(results)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Cache>, SolveError> {
+        self.cache
+            .lock()
+            .map_err(|_| SolveError::Internal("the cache lock is poisoned".to_string()))
+    }
+}
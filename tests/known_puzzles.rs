@@ -0,0 +1,92 @@
+// Puzzles from the show, with a solution known to be right (checked with `evaluate`):
+// the solver must find an exact solution to each, with any number of workers
+use deschiffres::{evaluate, solve, solve_with_workers};
+
+struct Known {
+    tiles: [i32; 6],
+    target: i32,
+    solution: &'static str,
+}
+
+const KNOWN: [Known; 5] = [
+    // The most famous one (1997): the 4 large tiles, and a solution going through 23 850
+    Known {
+        tiles: [25, 50, 75, 100, 3, 6],
+        target: 952,
+        solution: "((100 + 6) * 3 * 75 - 50) / 25",
+    },
+    // A single solution, with 5 operations
+    Known {
+        tiles: [5, 25, 2, 50, 100, 10],
+        target: 281,
+        solution: "5 + (2 + 10) * (25 - 100 / 50)",
+    },
+    Known {
+        tiles: [1, 3, 7, 10, 25, 50],
+        target: 765,
+        solution: "(25 - 10) * (50 + 1)",
+    },
+    Known {
+        tiles: [75, 50, 2, 3, 8, 7],
+        target: 812,
+        solution: "(50 + 8) * 7 * 2",
+    },
+    Known {
+        tiles: [5, 25, 2, 50, 100, 10],
+        target: 250,
+        solution: "5 * 50",
+    },
+];
+
+// The numbers written in an expression
+fn numbers_in(expression: &str) -> Vec<i32> {
+    expression
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_known_solutions() {
+    for known in &KNOWN {
+        assert_eq!(
+            evaluate(known.solution),
+            Ok(known.target),
+            "{}",
+            known.solution
+        );
+
+        let mut tiles = known.tiles.to_vec();
+        for number in numbers_in(known.solution) {
+            let i = tiles.iter().position(|x| *x == number).unwrap();
+            tiles.swap_remove(i);
+        }
+    }
+}
+
+#[test]
+fn test_known_puzzles() {
+    for known in &KNOWN {
+        for workers in [0, 4] {
+            let result = solve_with_workers(&known.tiles, known.target, 0, workers)
+                .unwrap()
+                .unwrap_or_else(|| panic!("no solution to {}", known.target));
+
+            assert_eq!(result.value, known.target);
+            assert_eq!(result.verify(), Ok(()));
+            assert!(result.uses_valid_tiles(&known.tiles));
+            assert_eq!(evaluate(&result.as_infix()), Ok(known.target));
+        }
+    }
+}
+
+#[test]
+fn test_known_impossible() {
+    // 830 and 832 are one away, but there is no way to 831
+    let tiles = [5, 25, 2, 50, 100, 10];
+    assert!(solve(&tiles, 831, 0).unwrap().is_none());
+
+    let result = solve(&tiles, 831, 1).unwrap().unwrap();
+    assert_eq!((result.value - 831).abs(), 1);
+}